@@ -0,0 +1,38 @@
+use gstreamer::{ElementFactory, ElementFactoryType, PadDirection, Rank};
+use messages::sink_management::Capabilities;
+use std::collections::BTreeSet;
+
+/// Collects the formats the installed GStreamer plugins can handle. Requires
+/// GStreamer to be initialized.
+pub fn probe_capabilities() -> Capabilities {
+    let containers = sink_caps_names(ElementFactoryType::DEMUXER | ElementFactoryType::PARSER);
+    let codecs = sink_caps_names(ElementFactoryType::DECODER);
+
+    Capabilities {
+        audio: true,
+        video: false,
+        containers,
+        codecs,
+        seekable: true,
+        volume: true,
+        output_selection: true,
+    }
+}
+
+fn sink_caps_names(factory_type: ElementFactoryType) -> Vec<String> {
+    let mut names = BTreeSet::new();
+
+    for factory in ElementFactory::factories_with_type(factory_type, Rank::Marginal) {
+        for template in factory.static_pad_templates() {
+            if template.direction() != PadDirection::Sink {
+                continue;
+            }
+
+            for structure in template.caps().iter() {
+                names.insert(structure.name().to_string());
+            }
+        }
+    }
+
+    names.into_iter().collect()
+}
@@ -0,0 +1,191 @@
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use log::LevelFilter;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+const DEFAULT_HUB_URL: &str = "ws://127.0.0.1:9024";
+const DEFAULT_NAME: &str = "Local test sink";
+
+/// Command line arguments, each of them overrides the configuration file.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Path to a JSON configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// WebSocket address of the hub's sink management endpoint
+    #[arg(long)]
+    hub_url: Option<String>,
+
+    /// Name of the sink displayed in the web interface
+    #[arg(short, long)]
+    name: Option<String>,
+
+    /// Name of the preferred PulseAudio output device
+    #[arg(short, long)]
+    device: Option<String>,
+
+    /// Stable identifier of the sink, overrides the one stored in the id file
+    #[arg(long)]
+    id: Option<uuid::Uuid>,
+
+    /// File storing the generated identifier of the sink
+    #[arg(long)]
+    id_file: Option<PathBuf>,
+
+    /// Log level (off, error, warn, info, debug, trace)
+    #[arg(long)]
+    log_level: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    hub_url: Option<String>,
+    name: Option<String>,
+    device: Option<String>,
+    id: Option<uuid::Uuid>,
+    id_file: Option<PathBuf>,
+    log_level: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    pub hub_url: url::Url,
+    pub name: String,
+    pub device: Option<String>,
+    pub id: uuid::Uuid,
+    pub log_level: Option<LevelFilter>,
+}
+
+pub fn load_config() -> Result<Config> {
+    let args = Args::parse();
+
+    let file = match &args.config {
+        Some(path) => read_config_file(path)?,
+        None => ConfigFile::default(),
+    };
+
+    let hub_url = args
+        .hub_url
+        .or(file.hub_url)
+        .unwrap_or_else(|| DEFAULT_HUB_URL.to_string());
+
+    let name = args
+        .name
+        .or(file.name)
+        .unwrap_or_else(|| DEFAULT_NAME.to_string());
+
+    let log_level = args
+        .log_level
+        .or(file.log_level)
+        .map(|level| parse_log_level(&level))
+        .transpose()?;
+
+    let id = match args.id.or(file.id) {
+        Some(id) => id,
+        None => load_or_create_id(
+            &args
+                .id_file
+                .or(file.id_file)
+                .unwrap_or_else(default_id_file),
+        )?,
+    };
+
+    Ok(Config {
+        hub_url: parse_hub_url(&hub_url)?,
+        name: validate_name(name)?,
+        device: args
+            .device
+            .or(file.device)
+            .map(validate_device)
+            .transpose()?,
+        id,
+        log_level,
+    })
+}
+
+fn read_config_file(path: &std::path::Path) -> Result<ConfigFile> {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    let file = File::open(path)
+        .map_err(|e| anyhow!("Cannot open configuration file {:?}: {}", path, e))?;
+    let reader = BufReader::new(file);
+
+    serde_json::from_reader(reader)
+        .map_err(|e| anyhow!("Invalid configuration file {:?}: {}", path, e))
+}
+
+fn default_id_file() -> PathBuf {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    state_dir.join(env!("CARGO_PKG_NAME")).join("sink-id")
+}
+
+/// Reads the sink identifier from `path`, generating and storing a new one
+/// if the file does not exist yet.
+fn load_or_create_id(path: &std::path::Path) -> Result<uuid::Uuid> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => content
+            .trim()
+            .parse()
+            .map_err(|e| anyhow!("Invalid sink id in {:?}: {}", path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let id = uuid::Uuid::new_v4();
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| anyhow!("Cannot create directory {:?}: {}", parent, e))?;
+            }
+            std::fs::write(path, id.to_string())
+                .map_err(|e| anyhow!("Cannot store sink id in {:?}: {}", path, e))?;
+
+            Ok(id)
+        }
+        Err(e) => Err(anyhow!("Cannot read sink id from {:?}: {}", path, e)),
+    }
+}
+
+fn parse_hub_url(hub_url: &str) -> Result<url::Url> {
+    let url =
+        url::Url::parse(hub_url).map_err(|e| anyhow!("Invalid hub url {}: {}", hub_url, e))?;
+
+    if !matches!(url.scheme(), "ws" | "wss") {
+        bail!("Hub url {} must use the ws or wss scheme", hub_url);
+    }
+
+    if url.host().is_none() {
+        bail!("Hub url {} has no host", hub_url);
+    }
+
+    Ok(url)
+}
+
+fn validate_name(name: String) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Sink name cannot be empty");
+    }
+
+    Ok(name.to_string())
+}
+
+fn validate_device(device: String) -> Result<String> {
+    if device.trim().is_empty() {
+        bail!("Output device name cannot be empty");
+    }
+
+    Ok(device)
+}
+
+fn parse_log_level(level: &str) -> Result<LevelFilter> {
+    level
+        .parse()
+        .map_err(|_| anyhow!("Invalid log level {}", level))
+}
@@ -0,0 +1,317 @@
+use crate::config::Config;
+use crate::player::{Player, PlayerMessage};
+use crate::pulsewatcher::PulseMessage;
+use crate::state::SinkState;
+use anyhow::{bail, Result};
+use futures_util::{SinkExt, StreamExt};
+use gstreamer::ClockTime;
+use log::{debug, info, warn};
+use messages::sink_management::{
+    AudioOutput, Capabilities, PlaybackState, Seek, SinkRequest, SinkResponse,
+};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio::sync::mpsc::UnboundedReceiver as Receiver;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+type HubStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+pub struct HubConnection {
+    config: Config,
+    capabilities: Capabilities,
+    player: Player,
+    state: SinkState,
+    /// Position requested with `Play`, applied once the player started.
+    start_position: Option<ClockTime>,
+    reconnect_delay: Duration,
+    pulse_receiver: Receiver<PulseMessage>,
+    player_receiver: Receiver<PlayerMessage>,
+}
+
+impl HubConnection {
+    pub fn new(
+        config: Config,
+        capabilities: Capabilities,
+        player: Player,
+        pulse_receiver: Receiver<PulseMessage>,
+        player_receiver: Receiver<PlayerMessage>,
+    ) -> Self {
+        Self {
+            config,
+            capabilities,
+            player,
+            state: SinkState::default(),
+            start_position: None,
+            reconnect_delay: INITIAL_RECONNECT_DELAY,
+            pulse_receiver,
+            player_receiver,
+        }
+    }
+
+    /// Keeps the sink connected to the hub, reconnecting with exponential
+    /// backoff whenever the connection cannot be established or gets lost.
+    pub async fn run(&mut self) -> Result<()> {
+        loop {
+            match tokio_tungstenite::connect_async(self.config.hub_url.clone()).await {
+                Ok((ws_stream, _http_response)) => {
+                    info!("Connected to hub {}", self.config.hub_url);
+
+                    if let Err(e) = self.run_session(ws_stream).await {
+                        warn!("Connection to hub lost: {}", e);
+                    }
+                }
+                Err(e) => {
+                    warn!("WebSocket connection to hub failed: {}", e);
+                }
+            }
+
+            info!("Reconnecting to hub in {:?}", self.reconnect_delay);
+            self.wait(self.reconnect_delay).await;
+            self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Waits for the given time while keeping track of local state changes.
+    async fn wait(&mut self, delay: Duration) {
+        let sleep = tokio::time::sleep(delay);
+        tokio::pin!(sleep);
+
+        loop {
+            select! {
+                _ = &mut sleep => return,
+
+                Some(msg) = self.pulse_receiver.recv() => {
+                    if let Some(request) = create_pulse_request(msg, &self.config) {
+                        self.state.update(&request);
+                    }
+                }
+
+                Some(msg) = self.player_receiver.recv() => {
+                    if let Some(request) = create_player_request(msg, self.state.resource()) {
+                        self.state.update(&request);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_session(&mut self, ws_stream: HubStream) -> Result<()> {
+        let (mut ws_write, mut ws_read) = ws_stream.split();
+
+        let register_request = SinkRequest::Register {
+            id: self.config.id,
+            name: self.config.name.clone(),
+            capabilities: self.capabilities.clone(),
+            playback: self.state.snapshot(),
+        };
+        ws_write.send(to_message(&register_request)?).await?;
+        ws_write
+            .send(to_message(&create_volume_request(&self.player))?)
+            .await?;
+        for request in self.state.restore_requests() {
+            ws_write.send(to_message(&request)?).await?;
+        }
+
+        loop {
+            select! {
+                msg = ws_read.next() => {
+                    debug!("WebSocket message: {:?}", msg);
+                    match msg {
+                        Some(msg) => self.handle_hub_message(msg?)?,
+                        None => bail!("Connection to Hub closed"),
+                    }
+                }
+
+                Some(msg) = self.pulse_receiver.recv() => {
+                    debug!("PulseAudio message: {:?}", msg);
+                    if let Some(request) = create_pulse_request(msg, &self.config) {
+                        self.state.update(&request);
+                        ws_write.send(to_message(&request)?).await?;
+                    }
+                }
+
+                Some(msg) = self.player_receiver.recv() => {
+                    debug!("Player message: {:?}", msg);
+                    self.seek_to_start_position(&msg);
+                    if let Some(request) = create_player_request(msg, self.state.resource()) {
+                        self.state.update(&request);
+                        ws_write.send(to_message(&request)?).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn seek_to_start_position(&mut self, msg: &PlayerMessage) {
+        if let PlayerMessage::StateChanged(gstreamer_player::PlayerState::Playing) = msg {
+            if let Some(position) = self.start_position.take() {
+                debug!("Resuming at {}", position);
+                self.player.seek(position);
+            }
+        }
+    }
+
+    fn handle_hub_message(&mut self, msg: Message) -> Result<()> {
+        match msg {
+            Message::Text(msg) => {
+                let msg: SinkResponse = match serde_json::from_str(&msg) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        warn!("HubConnection message deserialization failed: {:?}", e);
+                        return Ok(());
+                    }
+                };
+
+                match msg {
+                    SinkResponse::Play {
+                        resource,
+                        url,
+                        start_ms,
+                    } => {
+                        self.state.start(resource);
+                        self.start_position = start_ms.map(ClockTime::from_mseconds);
+                        self.player.set_uri(&url);
+                        self.player.play();
+                    }
+                    SinkResponse::Pause => {
+                        self.player.pause();
+                    }
+                    SinkResponse::Stop => {
+                        self.player.stop();
+                    }
+                    SinkResponse::Seek(seek) => {
+                        self.player.seek(seek_target(&self.player, seek));
+                    }
+                    SinkResponse::SetVolume(volume) => {
+                        self.player.set_volume(volume);
+                    }
+                    SinkResponse::SetMute(mute) => {
+                        self.player.set_mute(mute);
+                    }
+                    SinkResponse::SetOutput(output) => {
+                        self.player.set_audio_device(&output);
+                    }
+                    SinkResponse::Registered => {
+                        info!("Registered in hub as {}", self.config.id);
+                        self.reconnect_delay = INITIAL_RECONNECT_DELAY;
+                    }
+                    SinkResponse::RegistrationRejected(reason) => {
+                        bail!("Registration rejected by hub: {}", reason);
+                    }
+                    SinkResponse::Dummy => {}
+                }
+            }
+            // Pings are answered by tungstenite itself.
+            Message::Ping(_) | Message::Pong(_) => {}
+            Message::Close(close_frame) => {
+                debug!("Closing HubConnection: {:?}", close_frame);
+                bail!("Connection to Hub closed")
+            }
+            _ => {
+                warn!("HubConnection unsupported message type");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn to_message(request: &SinkRequest) -> Result<Message> {
+    let request = serde_json::to_string(request)?;
+    Ok(Message::Text(request))
+}
+
+fn create_pulse_request(msg: PulseMessage, config: &Config) -> Option<SinkRequest> {
+    match msg {
+        PulseMessage::StateChanged(state) => {
+            info!("PulseAudio state changed: {:?}", state);
+            None
+        }
+        PulseMessage::SinksUpdated(sinks) => {
+            if let Some(device) = &config.device {
+                if !sinks.iter().any(|sink| &sink.name == device) {
+                    warn!("Preferred output device {} is not available", device);
+                }
+            }
+
+            Some(SinkRequest::OutputsChanged(
+                sinks
+                    .into_iter()
+                    .map(|sink| AudioOutput {
+                        name: sink.name,
+                        description: sink.description,
+                        default: sink.default,
+                    })
+                    .collect(),
+            ))
+        }
+    }
+}
+
+fn seek_target(player: &Player, seek: Seek) -> ClockTime {
+    let target = match seek {
+        Seek::Absolute(position_ms) => ClockTime::from_mseconds(position_ms),
+        Seek::Relative(offset_ms) => {
+            let position = player.position().unwrap_or(ClockTime::ZERO);
+            let offset = ClockTime::from_mseconds(offset_ms.unsigned_abs());
+            if offset_ms < 0 {
+                position.saturating_sub(offset)
+            } else {
+                position.saturating_add(offset)
+            }
+        }
+    };
+
+    match player.duration() {
+        Some(duration) => target.min(duration),
+        None => target,
+    }
+}
+
+fn create_volume_request(player: &Player) -> SinkRequest {
+    SinkRequest::VolumeChanged {
+        volume: player.volume(),
+        muted: player.is_muted(),
+    }
+}
+
+fn to_playback_state(state: gstreamer_player::PlayerState) -> Option<PlaybackState> {
+    match state {
+        gstreamer_player::PlayerState::Stopped => Some(PlaybackState::Stopped),
+        gstreamer_player::PlayerState::Buffering => Some(PlaybackState::Buffering),
+        gstreamer_player::PlayerState::Paused => Some(PlaybackState::Paused),
+        gstreamer_player::PlayerState::Playing => Some(PlaybackState::Playing),
+        _ => None,
+    }
+}
+
+fn create_player_request(msg: PlayerMessage, resource: Option<uuid::Uuid>) -> Option<SinkRequest> {
+    let request = match msg {
+        PlayerMessage::StateChanged(state) => match to_playback_state(state) {
+            Some(state) => SinkRequest::StateChanged(state),
+            None => {
+                warn!("Unknown player state: {:?}", state);
+                return None;
+            }
+        },
+        PlayerMessage::PositionUpdated { position, duration } => SinkRequest::PositionChanged {
+            resource,
+            position_ms: position.mseconds(),
+            duration_ms: duration.map(|d| d.mseconds()),
+        },
+        PlayerMessage::EndOfStream => SinkRequest::EndOfStream,
+        PlayerMessage::Buffering(percent) => SinkRequest::Buffering(percent.clamp(0, 100) as u32),
+        PlayerMessage::Error(error) => SinkRequest::Error(error),
+        PlayerMessage::TitleChanged(title) => SinkRequest::TitleChanged(title),
+        PlayerMessage::VolumeChanged { volume, muted } => {
+            SinkRequest::VolumeChanged { volume, muted }
+        }
+        PlayerMessage::OutputChanged(output) => SinkRequest::OutputChanged(output),
+    };
+
+    Some(request)
+}
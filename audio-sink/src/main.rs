@@ -1,105 +1,76 @@
+mod capabilities;
+mod config;
+mod hub_connection;
 mod player;
 mod pulsewatcher;
+mod state;
 
-use std::sync::Arc;
-
-use anyhow::{bail, Result};
-use futures_util::{SinkExt, StreamExt};
-use log::{debug, info, warn};
-use messages::sink_management::{SinkRequest, SinkResponse};
-use player::Player;
+use anyhow::Result;
+use capabilities::probe_capabilities;
+use hub_connection::HubConnection;
+use log::{debug, info};
+use player::{Player, PlayerMessage};
 use pulsewatcher::{PulseMessage, PulseWatcher};
-use tokio::{select, sync::mpsc::unbounded_channel as channel};
-use tokio_tungstenite::tungstenite::Message;
-
-async fn handle_hub_message(msg: Message, player: Arc<Player>) -> Result<()> {
-    match msg {
-        Message::Text(msg) => {
-            let msg: SinkResponse = serde_json::from_str(&msg).unwrap();
-            match msg {
-                SinkResponse::Play(path) => {
-                    player.set_uri(&format!("file://{}", path.to_str().unwrap()));
-                    player.play();
-                }
-                SinkResponse::Pause => {
-                    player.pause();
-                }
-                SinkResponse::Stop => {
-                    player.stop();
-                }
-                SinkResponse::Dummy => {}
-            }
-        }
-        Message::Close(close_frame) => {
-            debug!("Closing HubConnection: {:?}", close_frame);
-            bail!("Connection to Hub closed")
-        }
-        _ => {
-            warn!("HubConnection unsupported message type");
-        }
-    }
-
-    Ok(())
-}
+use tokio::sync::mpsc::unbounded_channel as channel;
 
-fn create_register_request() -> Result<Message> {
-    let request = SinkRequest::Register {
-        name: "Local test sink".to_string(),
+fn init_logger(level: Option<log::LevelFilter>) {
+    let mut builder = if cfg!(debug_assertions) {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
+    } else {
+        env_logger::Builder::from_default_env()
     };
-    let request = serde_json::to_string(&request)?;
-    Ok(Message::Text(request))
+
+    if let Some(level) = level {
+        builder.filter_level(level);
+    }
+
+    builder.init();
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    if cfg!(debug_assertions) {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
-    } else {
-        env_logger::init();
-    }
+    let config = config::load_config()?;
+
+    init_logger(config.log_level);
 
     info!(
         "Starting {} {}",
         env!("CARGO_PKG_NAME"),
         env!("CARGO_PKG_VERSION")
     );
+    debug!("{:#?}", config);
 
-    let (pulse_sender, mut pulse_receiver) = channel::<PulseMessage>();
+    info!("Using sink id {}", config.id);
+
+    let (pulse_sender, pulse_receiver) = channel::<PulseMessage>();
 
     let pulse_audio_connection = tokio::task::spawn_blocking(move || {
         let pulse_watcher = PulseWatcher::new(pulse_sender).unwrap();
         pulse_watcher.run().unwrap()
     });
 
-    let player = Player::new()?;
-    let player = Arc::new(player);
-
-    let (ws_stream, _http_response) = tokio_tungstenite::connect_async(
-        url::Url::parse("ws://127.0.0.1:9024").expect("Valid Hub url"),
-    )
-    .await
-    .expect("WebSocket connection to Hub failed");
-
-    let (mut ws_write, mut ws_read) = ws_stream.split();
-
-    let register_request = create_register_request()?;
-    ws_write.send(register_request).await?;
-
-    loop {
-        select! {
-            Some(msg) = ws_read.next() => {
-                debug!("WebSocket message: {:?}", msg);
-                if (handle_hub_message(msg?, player.clone()).await).is_err() {
-                    break;
-                }
-            }
-
-            Some(msg) = pulse_receiver.recv() => {
-                debug!("PulseAudio message: {:?}", msg);
-            }
-        }
+    let (player_sender, player_receiver) = channel::<PlayerMessage>();
+
+    let player = Player::new(player_sender)?;
+    if let Some(device) = &config.device {
+        player.set_audio_device(device);
     }
 
+    let capabilities = probe_capabilities();
+    debug!(
+        "Supported containers: {:?}, codecs: {:?}",
+        capabilities.containers, capabilities.codecs
+    );
+
+    let mut hub_connection = HubConnection::new(
+        config,
+        capabilities,
+        player,
+        pulse_receiver,
+        player_receiver,
+    );
+    hub_connection.run().await?;
+
     tokio::try_join!(pulse_audio_connection)?;
 
     Ok(())
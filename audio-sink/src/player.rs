@@ -1,14 +1,45 @@
 use anyhow::Result;
 use gstreamer::prelude::*;
 use log::{debug, error};
+use std::sync::Mutex;
+use tokio::sync::mpsc::UnboundedSender as Sender;
+
+type PlayerSender = Sender<PlayerMessage>;
+
+/// Interval of `PlayerMessage::PositionUpdated` notifications in milliseconds.
+const POSITION_UPDATE_INTERVAL: u32 = 1000;
+
+#[derive(Debug)]
+pub enum PlayerMessage {
+    StateChanged(gstreamer_player::PlayerState),
+    PositionUpdated {
+        position: gstreamer::ClockTime,
+        duration: Option<gstreamer::ClockTime>,
+    },
+    EndOfStream,
+    Buffering(i32),
+    Error(String),
+    TitleChanged(String),
+    VolumeChanged {
+        volume: f64,
+        muted: bool,
+    },
+    OutputChanged(String),
+}
+
+fn send_message(sender: &PlayerSender, msg: PlayerMessage) {
+    if let Err(e) = sender.send(msg) {
+        error!("Error while sending player message: {:?}", e);
+    }
+}
 
 pub struct Player {
     inner: gstreamer_player::Player,
-    _sink: gstreamer::Element,
+    sink: gstreamer::Element,
 }
 
 impl Player {
-    pub fn new() -> Result<Self> {
+    pub fn new(sender: PlayerSender) -> Result<Self> {
         gstreamer::init()?;
 
         let sink = gstreamer::ElementFactory::make("pulsesink").build()?;
@@ -17,27 +48,97 @@ impl Player {
             gstreamer_player::PlayerSignalDispatcher::NONE,
         );
 
-        inner.connect_error(|_, err| {
+        let mut config = inner.config();
+        config.set_position_update_interval(POSITION_UPDATE_INTERVAL);
+        inner.set_config(config)?;
+
+        let error_sender = sender.clone();
+        inner.connect_error(move |_, err| {
             error!("{:?}", err);
+            send_message(&error_sender, PlayerMessage::Error(err.to_string()));
         });
 
-        inner.connect_state_changed(|_, state| {
+        let state_sender = sender.clone();
+        inner.connect_state_changed(move |_, state| {
             debug!("state changed: {:?}", state);
+            send_message(&state_sender, PlayerMessage::StateChanged(state));
         });
 
-        inner.connect_buffering(|_, p| {
+        let position_sender = sender.clone();
+        inner.connect_position_updated(move |zelf, position| {
+            if let Some(position) = position {
+                send_message(
+                    &position_sender,
+                    PlayerMessage::PositionUpdated {
+                        position,
+                        duration: zelf.duration(),
+                    },
+                );
+            }
+        });
+
+        let buffering_sender = sender.clone();
+        inner.connect_buffering(move |_, p| {
             debug!("buffering {}", p);
+            send_message(&buffering_sender, PlayerMessage::Buffering(p));
+        });
+
+        let volume_sender = sender.clone();
+        inner.connect_volume_changed(move |zelf| {
+            send_message(
+                &volume_sender,
+                PlayerMessage::VolumeChanged {
+                    volume: zelf.volume(),
+                    muted: zelf.is_muted(),
+                },
+            );
+        });
+
+        let mute_sender = sender.clone();
+        inner.connect_mute_changed(move |zelf| {
+            send_message(
+                &mute_sender,
+                PlayerMessage::VolumeChanged {
+                    volume: zelf.volume(),
+                    muted: zelf.is_muted(),
+                },
+            );
+        });
+
+        let title_sender = sender.clone();
+        let last_title = Mutex::new(None::<String>);
+        inner.connect_media_info_updated(move |_, info| {
+            let title = match info.title() {
+                Some(title) => title.to_string(),
+                None => return,
+            };
+
+            let mut last_title = last_title.lock().unwrap();
+            if last_title.as_ref() != Some(&title) {
+                debug!("title changed: {}", title);
+                *last_title = Some(title.clone());
+                send_message(&title_sender, PlayerMessage::TitleChanged(title));
+            }
         });
 
-        inner.connect_notify(Some("current-device"), |zelf, _| {
-            let current_device = zelf.property::<String>("current-device");
+        let eos_sender = sender.clone();
+        inner.connect_end_of_stream(move |_| {
+            debug!("end of stream");
+            send_message(&eos_sender, PlayerMessage::EndOfStream);
+        });
+
+        sink.connect_notify(Some("current-device"), move |zelf, _| {
+            let current_device = zelf.property::<Option<String>>("current-device");
             debug!("current device changed: {:?}", current_device);
+            if let Some(device) = current_device {
+                send_message(&sender, PlayerMessage::OutputChanged(device));
+            }
         });
 
         let pipeline = inner.pipeline();
         pipeline.set_property("audio-sink", &sink);
 
-        Ok(Self { inner, _sink: sink })
+        Ok(Self { inner, sink })
     }
 
     pub fn set_uri(&self, uri: &str) {
@@ -56,13 +157,35 @@ impl Player {
         self.inner.pause();
     }
 
-    // pub fn set_volume(&self, volume: f64) {
-    //     self.inner.set_volume(volume);
-    // }
+    pub fn seek(&self, position: gstreamer::ClockTime) {
+        self.inner.seek(position);
+    }
+
+    pub fn position(&self) -> Option<gstreamer::ClockTime> {
+        self.inner.position()
+    }
+
+    pub fn duration(&self) -> Option<gstreamer::ClockTime> {
+        self.inner.duration()
+    }
+
+    pub fn volume(&self) -> f64 {
+        self.inner.volume()
+    }
+
+    pub fn set_volume(&self, volume: f64) {
+        self.inner.set_volume(volume);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.inner.is_muted()
+    }
+
+    pub fn set_mute(&self, mute: bool) {
+        self.inner.set_mute(mute);
+    }
 
-    // pub fn set_audio_device(&self, device_name: impl ToString) {
-    //     self.sink
-    //         .set_property("device", &device_name.to_string())
-    //         .unwrap();
-    // }
+    pub fn set_audio_device(&self, device_name: &str) {
+        self.sink.set_property("device", device_name);
+    }
 }
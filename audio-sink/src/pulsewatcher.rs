@@ -1,12 +1,13 @@
 use anyhow::{anyhow, Result};
 use log::{debug, error};
-use pulse::{callbacks::ListResult, context::subscribe::InterestMaskSet};
+use pulse::callbacks::ListResult;
+use pulse::context::subscribe::{Facility, InterestMaskSet, Operation};
 use pulse::{context::Context, mainloop::api::Mainloop as MainloopTrait};
 use pulse::{
     context::State,
     mainloop::standard::{IterateResult, Mainloop},
 };
-use std::{cell::RefCell, ops::Deref, rc::Rc, sync::Arc};
+use std::{cell::RefCell, ops::Deref, rc::Rc};
 use tokio::sync::mpsc::UnboundedSender as Sender;
 
 type PulseSender = Sender<PulseMessage>;
@@ -17,26 +18,33 @@ pub enum PulseState {
     Disconnected,
 }
 
+#[derive(Debug, Clone)]
+pub struct PulseSink {
+    pub name: String,
+    pub description: String,
+    pub default: bool,
+}
+
 #[derive(Debug)]
 pub enum PulseMessage {
     StateChanged(PulseState),
-    SinksUpdated(Vec<String>),
+    SinksUpdated(Vec<PulseSink>),
 }
 
 pub struct PulseWatcher {
-    mainloop: Arc<RefCell<Mainloop>>,
-    context: Arc<RefCell<Context>>,
+    mainloop: Rc<RefCell<Mainloop>>,
+    context: Rc<RefCell<Context>>,
     sender: PulseSender,
 }
 
 impl PulseWatcher {
     pub fn new(sender: PulseSender) -> Result<PulseWatcher> {
         let mainloop =
-            Arc::new(RefCell::new(Mainloop::new().ok_or_else(|| {
+            Rc::new(RefCell::new(Mainloop::new().ok_or_else(|| {
                 anyhow!("Could not create a PulseAudio mainloop")
             })?));
 
-        let context = Arc::new(RefCell::new(
+        let context = Rc::new(RefCell::new(
             Context::new(mainloop.borrow().deref(), "ContextName")
                 .ok_or_else(|| anyhow!("Could not create a PulseAudio context"))?,
         ));
@@ -109,7 +117,7 @@ impl PulseWatcher {
     }
 
     fn subscribe_to_state_changes(&self) {
-        let inner = Arc::new(self.mainloop.borrow().inner());
+        let inner = Rc::new(self.mainloop.borrow().inner());
         let ctx = self.context.clone();
         let sender = self.sender.clone();
 
@@ -148,38 +156,59 @@ impl PulseWatcher {
 
         let sender = self.sender.clone();
         let ctx = self.context.clone();
-        let callback = move |_, op, _| {
-            if op == Some(pulse::context::subscribe::Operation::Changed) {
+        let callback = move |facility, op, _| {
+            if facility == Some(Facility::Sink) && op == Some(Operation::Changed) {
                 return;
             }
 
             debug!("Subscribed event notification");
-
-            let sinks = Rc::new(RefCell::new(vec![]));
-            let sender = sender.clone();
-            ctx.borrow_mut()
-                .introspect()
-                .get_sink_info_list(move |result| match result {
-                    ListResult::Item(item) => {
-                        let sink_desc = item.description.as_ref().map(|cow| cow.to_string());
-                        if let Some(desc) = sink_desc {
-                            sinks.borrow_mut().push(desc);
-                        }
-                    }
-                    ListResult::End => {
-                        let sinks = sinks.borrow_mut().clone();
-                        if let Err(e) = sender.send(PulseMessage::SinksUpdated(sinks)) {
-                            error!("Error while sending sink update: {:?}", e);
-                        }
-                    }
-                    ListResult::Error => {
-                        error!("Error while retrieving sinks");
-                    }
-                });
+            request_sinks(&ctx, &sender);
         };
 
-        let mut ctx = self.context.borrow_mut();
-        ctx.subscribe(InterestMaskSet::SINK, |_| {});
-        ctx.set_subscribe_callback(Some(Box::new(callback)));
+        {
+            let mut ctx = self.context.borrow_mut();
+            ctx.subscribe(InterestMaskSet::SINK | InterestMaskSet::SERVER, |_| {});
+            ctx.set_subscribe_callback(Some(Box::new(callback)));
+        }
+
+        request_sinks(&self.context, &self.sender);
     }
 }
+
+fn request_sinks(ctx: &Rc<RefCell<Context>>, sender: &PulseSender) {
+    let list_ctx = ctx.clone();
+    let sender = sender.clone();
+
+    ctx.borrow().introspect().get_server_info(move |info| {
+        let default_sink = info.default_sink_name.as_ref().map(|cow| cow.to_string());
+        let sinks = Rc::new(RefCell::new(vec![]));
+        let sender = sender.clone();
+
+        list_ctx
+            .borrow()
+            .introspect()
+            .get_sink_info_list(move |result| match result {
+                ListResult::Item(item) => {
+                    if let Some(name) = item.name.as_ref().map(|cow| cow.to_string()) {
+                        sinks.borrow_mut().push(PulseSink {
+                            default: default_sink.as_ref() == Some(&name),
+                            description: item
+                                .description
+                                .as_ref()
+                                .map_or_else(|| name.clone(), |cow| cow.to_string()),
+                            name,
+                        });
+                    }
+                }
+                ListResult::End => {
+                    let sinks = sinks.borrow_mut().clone();
+                    if let Err(e) = sender.send(PulseMessage::SinksUpdated(sinks)) {
+                        error!("Error while sending sink update: {:?}", e);
+                    }
+                }
+                ListResult::Error => {
+                    error!("Error while retrieving sinks");
+                }
+            });
+    });
+}
@@ -0,0 +1,87 @@
+use messages::sink_management::{AudioOutput, PlaybackSnapshot, PlaybackState, SinkRequest};
+
+/// Last known state of the sink, replayed to the hub after reconnecting.
+#[derive(Debug)]
+pub struct SinkState {
+    resource: Option<uuid::Uuid>,
+    state: PlaybackState,
+    position_ms: u64,
+    duration_ms: Option<u64>,
+    outputs: Option<Vec<AudioOutput>>,
+    current_output: Option<String>,
+}
+
+impl Default for SinkState {
+    fn default() -> Self {
+        Self {
+            resource: None,
+            state: PlaybackState::Stopped,
+            position_ms: 0,
+            duration_ms: None,
+            outputs: None,
+            current_output: None,
+        }
+    }
+}
+
+impl SinkState {
+    pub fn start(&mut self, resource: uuid::Uuid) {
+        self.resource = Some(resource);
+        self.position_ms = 0;
+        self.duration_ms = None;
+    }
+
+    pub fn resource(&self) -> Option<uuid::Uuid> {
+        self.resource
+    }
+
+    /// Records the state carried by a request that is about to be sent to the hub.
+    pub fn update(&mut self, request: &SinkRequest) {
+        match request {
+            SinkRequest::StateChanged(state) => self.state = *state,
+            SinkRequest::PositionChanged {
+                position_ms,
+                duration_ms,
+                ..
+            } => {
+                self.position_ms = *position_ms;
+                self.duration_ms = *duration_ms;
+            }
+            SinkRequest::EndOfStream => {
+                self.state = PlaybackState::Stopped;
+                self.resource = None;
+            }
+            SinkRequest::OutputsChanged(outputs) => self.outputs = Some(outputs.clone()),
+            SinkRequest::OutputChanged(output) => self.current_output = Some(output.clone()),
+            _ => {}
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<PlaybackSnapshot> {
+        if self.state == PlaybackState::Stopped {
+            return None;
+        }
+
+        Some(PlaybackSnapshot {
+            resource: self.resource?,
+            state: self.state,
+            position_ms: self.position_ms,
+            duration_ms: self.duration_ms,
+        })
+    }
+
+    /// Requests that restore the hub's view of the sink after registration.
+    pub fn restore_requests(&self) -> Vec<SinkRequest> {
+        let mut requests = Vec::new();
+
+        if let Some(outputs) = &self.outputs {
+            requests.push(SinkRequest::OutputsChanged(outputs.clone()));
+        }
+
+        if let Some(output) = &self.current_output {
+            requests.push(SinkRequest::OutputChanged(output.clone()));
+        }
+
+        requests
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, bail, Result};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use symphonia::core::io::MediaSource;
+
+/// Entries larger than this are not decompressed or read into memory.
+pub const MAX_INFLATED_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Compressed entries are probed for their tags from at most this many of
+/// their first bytes.
+const METADATA_READ_LIMIT: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Zip,
+    Tar,
+}
+
+fn format(path: &Path) -> Option<Format> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+
+    match extension.as_str() {
+        "zip" => Some(Format::Zip),
+        "tar" => Some(Format::Tar),
+        _ => None,
+    }
+}
+
+/// Checks whether `path` is a zip or tar archive by its extension.
+pub fn is_archive(path: &Path) -> bool {
+    format(path).is_some()
+}
+
+/// Where the content of an entry can be read from.
+pub enum EntryContent {
+    /// Stored uncompressed at `offset` within the archive file.
+    Slice { offset: u64, size: u64 },
+    /// Decompressed into memory.
+    Bytes(Vec<u8>),
+}
+
+/// Entry of an archive passed to the visitor of [`visit_entries`].
+pub struct VisitedEntry<'a> {
+    archive: &'a Path,
+    /// Offset and size of entries stored uncompressed.
+    stored: Option<(u64, u64)>,
+    content: &'a mut dyn Read,
+}
+
+impl VisitedEntry<'_> {
+    /// Source to probe the entry for its tags. Uncompressed entries are read
+    /// in place, of compressed ones at most [`METADATA_READ_LIMIT`] bytes are
+    /// decompressed into memory.
+    pub fn media_source(&mut self) -> Result<Box<dyn MediaSource>> {
+        match self.stored {
+            Some((offset, size)) => Ok(Box::new(FileSlice::open(self.archive, offset, size)?)),
+            None => {
+                let mut bytes = Vec::new();
+                self.content
+                    .take(METADATA_READ_LIMIT)
+                    .read_to_end(&mut bytes)?;
+                Ok(Box::new(Cursor::new(bytes)))
+            }
+        }
+    }
+}
+
+/// Calls `visit` with the name and the content of every regular file in the
+/// archive at `path`, reading the archive once. Content that `visit` does
+/// not read is skipped.
+pub fn visit_entries<F>(path: &Path, mut visit: F) -> Result<()>
+where
+    F: FnMut(&str, VisitedEntry),
+{
+    let file = File::open(path)?;
+
+    match format(path) {
+        Some(Format::Zip) => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index)?;
+                if entry.is_file() {
+                    let name = entry.name().to_string();
+                    let stored = (entry.compression() == zip::CompressionMethod::Stored)
+                        .then(|| (entry.data_start(), entry.size()));
+                    visit(
+                        &name,
+                        VisitedEntry {
+                            archive: path,
+                            stored,
+                            content: &mut entry,
+                        },
+                    );
+                }
+            }
+            Ok(())
+        }
+        Some(Format::Tar) => {
+            let mut archive = tar::Archive::new(BufReader::new(file));
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                if entry.header().entry_type().is_file() {
+                    let name = entry.path()?.to_string_lossy().into_owned();
+                    let stored = Some((entry.raw_file_position(), entry.size()));
+                    visit(
+                        &name,
+                        VisitedEntry {
+                            archive: path,
+                            stored,
+                            content: &mut entry,
+                        },
+                    );
+                }
+            }
+            Ok(())
+        }
+        None => Err(anyhow!("{} is no zip or tar archive", path.display())),
+    }
+}
+
+/// Region of a file holding an uncompressed entry, read like a file of its own.
+struct FileSlice {
+    file: File,
+    offset: u64,
+    size: u64,
+    position: u64,
+}
+
+impl FileSlice {
+    fn open(path: &Path, offset: u64, size: u64) -> Result<Self> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        Ok(Self {
+            file,
+            offset,
+            size,
+            position: 0,
+        })
+    }
+}
+
+impl Read for FileSlice {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.size - self.position;
+        let len = remaining.min(buf.len() as u64) as usize;
+        let read = self.file.read(&mut buf[..len])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for FileSlice {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        }
+        .ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Seek before start")
+        })?;
+
+        self.file.seek(SeekFrom::Start(self.offset + position))?;
+        self.position = position;
+        Ok(position)
+    }
+}
+
+impl MediaSource for FileSlice {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.size)
+    }
+}
+
+/// Reads the content of an entry into memory, refusing entries larger than
+/// [`MAX_INFLATED_SIZE`] whatever size their header claims.
+pub fn read_content(content: &mut dyn Read) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    content
+        .take(MAX_INFLATED_SIZE + 1)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_INFLATED_SIZE {
+        bail!("Entry exceeds {} bytes", MAX_INFLATED_SIZE);
+    }
+
+    Ok(bytes)
+}
+
+/// Locates the entry `name` of the archive at `path`. Uncompressed entries
+/// are read in place, compressed ones are decompressed into memory.
+pub fn open_entry(path: &Path, name: &str) -> Result<EntryContent> {
+    let file = File::open(path)?;
+    let missing = || anyhow!("{} has no entry {}", path.display(), name);
+
+    match format(path) {
+        Some(Format::Zip) => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+            let mut entry = archive.by_name(name).map_err(|_| missing())?;
+            if entry.compression() == zip::CompressionMethod::Stored {
+                return Ok(EntryContent::Slice {
+                    offset: entry.data_start(),
+                    size: entry.size(),
+                });
+            }
+
+            Ok(EntryContent::Bytes(read_content(&mut entry)?))
+        }
+        Some(Format::Tar) => {
+            let mut archive = tar::Archive::new(BufReader::new(file));
+            for entry in archive.entries()? {
+                let entry = entry?;
+                if entry.header().entry_type().is_file() && entry.path()?.to_string_lossy() == name
+                {
+                    return Ok(EntryContent::Slice {
+                        offset: entry.raw_file_position(),
+                        size: entry.size(),
+                    });
+                }
+            }
+            Err(missing())
+        }
+        None => Err(anyhow!("{} is no zip or tar archive", path.display())),
+    }
+}
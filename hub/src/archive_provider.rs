@@ -0,0 +1,319 @@
+use crate::archive::{is_archive, visit_entries};
+use crate::media_type::classify_extension;
+use crate::metadata::read_metadata_from;
+use crate::provider::{reconcile, Provider, Published, ResourceProviderInterface, ScanProgress};
+use crate::resource::{file_resource_id, provider_id, Location, Resource};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use messages::web_interface::{MediaType, ProviderKind, ScanStatus};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::sync::mpsc::{unbounded_channel as channel, UnboundedSender as Sender};
+use tokio::sync::watch;
+use walkdir::WalkDir;
+
+/// Tracks inside zip and tar archives, e.g. albums stored as one file. The
+/// entries are served by the media server without extracting them.
+pub struct ArchiveProvider {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub paths: Vec<String>,
+    commands: Sender<Command>,
+    status: watch::Receiver<ScanStatus>,
+}
+
+enum Command {
+    Rescan(Sender<ScanProgress>),
+}
+
+impl ArchiveProvider {
+    pub fn new(
+        name: String,
+        paths: Vec<String>,
+        extensions: Vec<String>,
+        known: Vec<Resource>,
+        resource_sender: Sender<ResourceProviderInterface>,
+    ) -> Self {
+        let (commands, mut command_receiver) = channel::<Command>();
+        let (status_sender, status) = watch::channel(ScanStatus::Pending);
+
+        let mut scanner = ArchiveScanner::new(name.clone(), paths.clone(), extensions, &known);
+
+        tokio::spawn(async move {
+            // Resources known from a previous run are published right away,
+            // the scan then only reports the differences to them.
+            if !known.is_empty()
+                && resource_sender
+                    .send(ResourceProviderInterface::Add(known))
+                    .is_err()
+            {
+                return;
+            }
+
+            let mut progress = None;
+            loop {
+                status_sender.send_replace(ScanStatus::Scanning);
+                let scan = tokio::task::spawn_blocking(move || {
+                    let messages = scanner.scan(progress);
+                    (scanner, messages)
+                })
+                .await;
+                status_sender.send_replace(ScanStatus::Idle);
+
+                let messages;
+                (scanner, messages) = match scan {
+                    Ok(scan) => scan,
+                    Err(e) => {
+                        log::error!("Archive scan failed: {}", e);
+                        return;
+                    }
+                };
+
+                for msg in messages {
+                    if resource_sender.send(msg).is_err() {
+                        return;
+                    }
+                }
+
+                progress = match command_receiver.recv().await {
+                    Some(Command::Rescan(progress)) => Some(progress),
+                    None => return,
+                };
+            }
+        });
+
+        Self {
+            id: provider_id(&name),
+            name,
+            paths,
+            commands,
+            status,
+        }
+    }
+}
+
+struct ArchiveScanner {
+    provider: String,
+    provider_id: uuid::Uuid,
+    paths: Vec<PathBuf>,
+    extensions: Vec<String>,
+    /// Every archive found by the previous scan.
+    archives: HashMap<PathBuf, ScannedArchive>,
+    known: Published,
+}
+
+/// Archive as it was when it was last read.
+struct ScannedArchive {
+    size: u64,
+    modified: Option<SystemTime>,
+    resources: Vec<Resource>,
+}
+
+impl ArchiveScanner {
+    fn new(
+        provider: String,
+        paths: Vec<String>,
+        extensions: Vec<String>,
+        known: &[Resource],
+    ) -> Self {
+        // Archives without playable entries are not part of the index, they
+        // are read again on the first scan.
+        let mut archives = HashMap::<PathBuf, ScannedArchive>::new();
+        for res in known {
+            if let (Location::Archive { archive, .. }, Some(size)) = (&res.location, res.size) {
+                archives
+                    .entry(archive.clone())
+                    .or_insert_with(|| ScannedArchive {
+                        size,
+                        modified: res.modified,
+                        resources: Vec::new(),
+                    })
+                    .resources
+                    .push(res.clone());
+            }
+        }
+
+        Self {
+            provider_id: provider_id(&provider),
+            provider,
+            paths: paths.into_iter().map(PathBuf::from).collect(),
+            extensions,
+            archives,
+            known: known.iter().map(|res| (res.uuid, res.clone())).collect(),
+        }
+    }
+
+    /// Indexes the archives under the provider paths and returns the
+    /// changes to the previous scan.
+    fn scan(&mut self, progress: Option<Sender<ScanProgress>>) -> Vec<ResourceProviderInterface> {
+        let mut report = ScanProgress {
+            provider: self.provider_id,
+            ..Default::default()
+        };
+        let mut archives = HashMap::new();
+
+        for (index, root) in self.paths.iter().enumerate() {
+            for entry in WalkDir::new(root).sort_by_key(|a| a.file_name().to_owned()) {
+                let path = match entry {
+                    Ok(entry) if entry.file_type().is_file() && is_archive(entry.path()) => {
+                        entry.into_path()
+                    }
+                    Ok(_) => continue,
+                    Err(e) => {
+                        log::error!("walkdir error: {:?}", e);
+                        report.errors.push(e.to_string());
+                        continue;
+                    }
+                };
+
+                report.files_visited += 1;
+                match self.archive(index, root, &path) {
+                    Ok(archive) => {
+                        report.resources_found += archive.resources.len() as u64;
+                        archives.insert(path, archive);
+                    }
+                    Err(e) => {
+                        log::error!("Cannot read archive {}: {}", path.display(), e);
+                        report.errors.push(format!("{}: {}", path.display(), e));
+                    }
+                }
+
+                if let Some(progress) = &progress {
+                    progress.send(report.clone()).ok();
+                    report.errors.clear();
+                }
+            }
+        }
+
+        self.archives = archives;
+        let resources = self
+            .archives
+            .values()
+            .flat_map(|archive| archive.resources.iter().cloned())
+            .collect();
+        let messages = reconcile(&mut self.known, resources);
+
+        report.finished = true;
+        if let Some(progress) = &progress {
+            progress.send(report).ok();
+        }
+
+        messages
+    }
+
+    /// Creates resources for the playable entries of an archive. Entries of
+    /// known archives are only read again if their size or modification
+    /// time changed.
+    fn archive(&self, index: usize, root: &Path, path: &Path) -> Result<ScannedArchive> {
+        let stat = std::fs::metadata(path)?;
+        let size = stat.len();
+        let modified = stat.modified().ok();
+
+        if let Some(known) = self.archives.get(path) {
+            let unchanged = modified.is_some()
+                && known.size == size
+                && known.modified == modified
+                && known
+                    .resources
+                    .iter()
+                    .all(|res| res.uuid == file_resource_id(&self.provider, index, &res.name));
+            if unchanged {
+                return Ok(ScannedArchive {
+                    size,
+                    modified,
+                    resources: known.resources.clone(),
+                });
+            }
+        }
+
+        let relative = path
+            .strip_prefix(root)
+            .ok()
+            .and_then(|relative| relative.to_str())
+            .ok_or_else(|| anyhow!("Unsupported path {}", path.display()))?;
+
+        let mut resources = Vec::new();
+        visit_entries(path, |entry, mut content| {
+            let entry_path = Path::new(entry);
+            let media_type = match classify_extension(entry_path) {
+                Some(media_type) if self.is_wanted(entry_path, media_type) => media_type,
+                _ => return,
+            };
+
+            let metadata = content
+                .media_source()
+                .and_then(|source| read_metadata_from(source, entry_path))
+                .unwrap_or_else(|e| {
+                    log::debug!("No metadata for {} in {}: {}", entry, path.display(), e);
+                    Default::default()
+                });
+
+            let name = format!("{}/{}", relative, entry);
+            resources.push(Resource {
+                uuid: file_resource_id(&self.provider, index, &name),
+                provider: self.provider_id,
+                name,
+                location: Location::Archive {
+                    archive: path.to_path_buf(),
+                    entry: entry.to_string(),
+                },
+                media_type,
+                metadata,
+                entries: Vec::new(),
+                resumable: false,
+                size: Some(size),
+                modified,
+            });
+        })?;
+
+        Ok(ScannedArchive {
+            size,
+            modified,
+            resources,
+        })
+    }
+
+    /// Entries with one of the configured extensions, audio if none are
+    /// configured.
+    fn is_wanted(&self, entry: &Path, media_type: MediaType) -> bool {
+        if self.extensions.is_empty() {
+            return media_type == MediaType::Audio;
+        }
+
+        entry.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+            self.extensions
+                .iter()
+                .any(|ext| ext.eq_ignore_ascii_case(e))
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for ArchiveProvider {
+    fn get_id(&self) -> uuid::Uuid {
+        self.id
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_kind(&self) -> ProviderKind {
+        ProviderKind::Archive
+    }
+
+    fn get_roots(&self) -> Vec<String> {
+        self.paths.clone()
+    }
+
+    fn get_scan_status(&self) -> ScanStatus {
+        *self.status.borrow()
+    }
+
+    fn rescan(&self, progress: Sender<ScanProgress>) -> anyhow::Result<()> {
+        self.commands
+            .send(Command::Rescan(progress))
+            .map_err(|_| anyhow!("Provider {} is not running", self.name))
+    }
+}
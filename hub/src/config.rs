@@ -8,9 +8,48 @@ pub struct Filesystem {
     pub extensions: Vec<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct Station {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Stream {
+    pub name: String,
+    #[serde(default)]
+    pub stations: Vec<Station>,
+    /// M3U or PLS file with further stations.
+    #[serde(default)]
+    pub playlist: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Podcast {
+    pub name: String,
+    /// Feed files or `http`/`https` URLs of RSS or Atom feeds.
+    pub feeds: Vec<String>,
+    /// Folder episodes are downloaded to, they are streamed if not set.
+    #[serde(default)]
+    pub download_path: Option<std::path::PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Archive {
+    pub name: String,
+    /// Folders searched for zip and tar archives.
+    pub paths: Vec<String>,
+    /// Extensions of the entries to offer, audio entries if empty.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub enum Provider {
     Filesystem(Filesystem),
+    Stream(Stream),
+    Podcast(Podcast),
+    Archive(Archive),
 }
 
 #[derive(Debug, Deserialize)]
@@ -18,9 +57,50 @@ pub struct Config {
     pub web_ui_address: std::net::SocketAddr,
     pub sink_management_address: std::net::SocketAddr,
 
+    /// Address of the HTTP server sinks fetch the resources from.
+    #[serde(default = "default_media_address")]
+    pub media_address: std::net::SocketAddr,
+    /// Base URL sinks use to reach the media server, derived from
+    /// `media_address` if not set.
+    #[serde(default)]
+    pub media_url: Option<String>,
+
+    /// File the resource catalogue is persisted to, nothing is persisted if
+    /// not set.
+    #[serde(default)]
+    pub index_path: Option<std::path::PathBuf>,
+    /// File the playlists created from the web interface are stored in,
+    /// they are lost on restart if not set.
+    #[serde(default)]
+    pub playlists_path: Option<std::path::PathBuf>,
+    /// File the playback positions of podcast episodes are kept in, so they
+    /// resume where they were left off after a restart.
+    #[serde(default)]
+    pub positions_path: Option<std::path::PathBuf>,
+
     pub providers: Vec<Provider>,
 }
 
+fn default_media_address() -> std::net::SocketAddr {
+    ([0, 0, 0, 0], 9025).into()
+}
+
+impl Config {
+    pub fn media_url(&self) -> String {
+        match &self.media_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => {
+                let mut address = self.media_address;
+                if address.ip().is_unspecified() {
+                    log::warn!("No media_url configured, sinks on other hosts cannot play media");
+                    address.set_ip([127, 0, 0, 1].into());
+                }
+                format!("http://{}", address)
+            }
+        }
+    }
+}
+
 pub fn load_config<P: AsRef<std::path::Path>>(path: P) -> Result<Config> {
     use std::fs::File;
     use std::io::BufReader;
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::time::timeout;
+use tokio_util::io::StreamReader;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time to fetch a whole document with [`Fetcher::fetch`], e.g. a feed.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time to wait for the response or the next part of the content of a
+/// document opened with [`Fetcher::open`], e.g. a download.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Content of a fetched document, read as it arrives.
+pub type Content = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Source of documents referenced by providers, e.g. podcast feeds and
+/// their enclosures. Exchangeable so the content can come from a stand-in.
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    async fn open(&self, location: &str) -> Result<Content>;
+
+    /// Reads the whole document into memory, for small ones like feeds.
+    async fn fetch(&self, location: &str) -> Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.open(location).await?.read_to_end(&mut content).await?;
+        Ok(content)
+    }
+}
+
+/// Reads local files and fetches `http` and `https` URLs. Requests to
+/// servers that stop responding time out, so they do not block the provider.
+pub struct DefaultFetcher {
+    client: reqwest::Client,
+}
+
+impl Default for DefaultFetcher {
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .expect("HTTP client");
+
+        Self { client }
+    }
+}
+
+fn is_http(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+fn timed_out(location: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        format!("{} did not respond in time", location),
+    )
+}
+
+#[async_trait]
+impl Fetcher for DefaultFetcher {
+    async fn open(&self, location: &str) -> Result<Content> {
+        if is_http(location) {
+            let response = timeout(READ_TIMEOUT, self.client.get(location).send())
+                .await
+                .map_err(|_| timed_out(location))??
+                .error_for_status()?;
+            let location = location.to_string();
+            let chunks = futures_util::stream::try_unfold(response, move |mut response| {
+                let location = location.clone();
+                async move {
+                    let chunk = timeout(READ_TIMEOUT, response.chunk())
+                        .await
+                        .map_err(|_| timed_out(&location))?
+                        .map_err(std::io::Error::other)?;
+                    Ok::<_, std::io::Error>(chunk.map(|chunk| (chunk, response)))
+                }
+            });
+            return Ok(Box::new(StreamReader::new(Box::pin(chunks))));
+        }
+
+        let path = location.strip_prefix("file://").unwrap_or(location);
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+        Ok(Box::new(file))
+    }
+
+    async fn fetch(&self, location: &str) -> Result<Vec<u8>> {
+        if is_http(location) {
+            let response = self
+                .client
+                .get(location)
+                .timeout(FETCH_TIMEOUT)
+                .send()
+                .await?
+                .error_for_status()?;
+            return Ok(response.bytes().await?.to_vec());
+        }
+
+        let mut content = Vec::new();
+        self.open(location).await?.read_to_end(&mut content).await?;
+        Ok(content)
+    }
+}
@@ -1,76 +1,631 @@
-use crate::provider::{Provider, ResourceProviderInterface};
-use crate::resource::Resource;
+use crate::media_type::{classify, is_playlist};
+use crate::metadata::read_metadata;
+use crate::playlist::{read_playlist, resolve};
+use crate::provider::{Provider, ResourceProviderInterface, ScanProgress};
+use crate::resource::{file_resource_id, provider_id, Location, Resource};
+use crate::resource_index::{Directories, ScannedDirectories};
+use anyhow::anyhow;
 use async_trait::async_trait;
-use tokio::sync::mpsc::UnboundedSender as Sender;
-use walkdir::WalkDir;
+use messages::web_interface::{MediaType, ProviderKind, ScanStatus};
+use notify::event::{AccessKind, AccessMode, CreateKind, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::{unbounded_channel as channel, UnboundedSender as Sender};
+use tokio::sync::watch;
 
 pub struct FilesystemProvider {
+    pub id: uuid::Uuid,
     pub name: String,
     pub paths: Vec<String>,
     pub extensions: Vec<String>,
+    commands: Sender<Command>,
+    status: watch::Receiver<ScanStatus>,
+    _watcher: Option<RecommendedWatcher>,
 }
 
+enum Command {
+    Rescan(Sender<ScanProgress>),
+}
+
+/// Number of visited files between two progress reports.
+const PROGRESS_INTERVAL: u64 = 100;
+
 impl FilesystemProvider {
     pub fn new(
         name: String,
         paths: Vec<String>,
         extensions: Vec<String>,
+        known: Vec<Resource>,
+        directories: ScannedDirectories,
         resource_sender: Sender<ResourceProviderInterface>,
     ) -> Self {
+        let (event_sender, mut event_receiver) = channel::<Event>();
+        let (commands, mut command_receiver) = channel::<Command>();
+        let (status_sender, status) = watch::channel(ScanStatus::Pending);
+        let watcher = create_watcher(&paths, event_sender)
+            .map_err(|e| log::error!("Cannot watch paths of provider {}: {:?}", name, e))
+            .ok();
+
         let zelf = Self {
-            name,
+            id: provider_id(&name),
+            name: name.clone(),
             paths: paths.clone(),
             extensions: extensions.clone(),
+            commands,
+            status,
+            _watcher: watcher,
         };
 
+        let scanner = Scanner::new(name, paths, extensions);
+        // Resources of unchanged directories are reused as they are, which is
+        // only right if they were found with the same paths and extensions.
+        let directories =
+            if directories.paths == scanner.paths && directories.extensions == scanner.extensions {
+                directories.modified
+            } else {
+                Directories::new()
+            };
         tokio::spawn(async move {
-            let res = fetch(paths, extensions);
-            resource_sender
-                .send(ResourceProviderInterface::Add(res))
-                .unwrap();
+            // Resources known from a previous run are published right away,
+            // the initial scan then only reports the differences to them and
+            // skips the directories that did not change since.
+            if !known.is_empty() {
+                resource_sender
+                    .send(ResourceProviderInterface::Add(known.clone()))
+                    .unwrap();
+            }
+
+            let index = FileIndex::new(scanner, known, directories);
+            status_sender.send_replace(ScanStatus::Scanning);
+            let mut update =
+                update_index(index, |index| index.rescan(ScanReporter::silent(), false)).await;
+            status_sender.send_replace(ScanStatus::Idle);
+
+            loop {
+                let (index, messages) = match update {
+                    Some(update) => update,
+                    None => return,
+                };
+
+                for msg in messages {
+                    if resource_sender.send(msg).is_err() {
+                        return;
+                    }
+                }
+
+                update = tokio::select! {
+                    Some(event) = event_receiver.recv() => {
+                        update_index(index, move |index| index.handle_event(event)).await
+                    }
+                    Some(command) = command_receiver.recv() => match command {
+                        Command::Rescan(progress) => {
+                            status_sender.send_replace(ScanStatus::Scanning);
+                            let reporter = ScanReporter::new(index.scanner.provider_id, progress);
+                            let update =
+                                update_index(index, move |index| index.rescan(reporter, true))
+                                    .await;
+                            status_sender.send_replace(ScanStatus::Idle);
+                            update
+                        }
+                    },
+                    else => return,
+                };
+            }
         });
 
         zelf
     }
 }
 
-fn fetch(paths: Vec<String>, extensions: Vec<String>) -> Vec<Resource> {
-    let mut resources = Vec::new();
+/// Applies `update` to the index on the blocking thread pool, as walking the
+/// paths and reading tags can take minutes for a large library. An update
+/// that panics is skipped, so a single bad file does not stop the provider.
+async fn update_index<F>(
+    mut index: FileIndex,
+    update: F,
+) -> Option<(FileIndex, Vec<ResourceProviderInterface>)>
+where
+    F: FnOnce(&mut FileIndex) -> Vec<ResourceProviderInterface> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let messages = std::panic::catch_unwind(AssertUnwindSafe(|| update(&mut index)))
+            .unwrap_or_else(|_| {
+                log::error!(
+                    "Updating the file index of {} failed",
+                    index.scanner.provider
+                );
+                Vec::new()
+            });
+        (index, messages)
+    })
+    .await
+    .map_err(|e| log::error!("Updating the file index failed: {}", e))
+    .ok()
+}
 
-    for path in &paths {
-        for entry in WalkDir::new(path)
-            .sort_by_key(|a| a.file_name().to_owned())
-            .into_iter()
-        {
-            match entry {
-                Ok(entry) => {
-                    let p = entry.path();
-                    if p.is_file() {
-                        let allowed_extension = p.extension().map_or_else(
-                            || false,
-                            |e| extensions.contains(&e.to_str().unwrap().to_string()),
-                        );
-
-                        if allowed_extension {
-                            resources.push(Resource {
-                                uuid: uuid::Uuid::new_v4(),
-                                name: p.strip_prefix(path).unwrap().to_str().unwrap().to_string(),
-                                path: p.to_path_buf(),
-                            })
-                        }
+fn create_watcher(paths: &[String], sender: Sender<Event>) -> notify::Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |event| match event {
+        Ok(event) => {
+            sender.send(event).ok();
+        }
+        Err(e) => log::error!("watch error: {:?}", e),
+    })?;
+
+    for path in paths {
+        watcher.watch(Path::new(path), RecursiveMode::Recursive)?;
+    }
+
+    Ok(watcher)
+}
+
+struct Scanner {
+    provider: String,
+    provider_id: uuid::Uuid,
+    paths: Vec<PathBuf>,
+    extensions: Vec<String>,
+}
+
+impl Scanner {
+    fn new(provider: String, paths: Vec<String>, extensions: Vec<String>) -> Self {
+        Self {
+            provider_id: provider_id(&provider),
+            provider,
+            paths: paths.into_iter().map(PathBuf::from).collect(),
+            extensions,
+        }
+    }
+
+    fn fetch(&self, scan: &mut Scan) {
+        for path in &self.paths {
+            self.fetch_path(path, scan);
+        }
+    }
+
+    /// Collects the resources in the directory `path` and below. Directories
+    /// whose modification time matches the one in `scan.unchanged` are not
+    /// read again.
+    fn fetch_path(&self, path: &Path, scan: &mut Scan) {
+        let modified = match std::fs::metadata(path).and_then(|stat| stat.modified()) {
+            Ok(modified) => modified,
+            Err(e) => {
+                log::error!("Cannot read {}: {}", path.display(), e);
+                scan.reporter.error(format!("{}: {}", path.display(), e));
+                return;
+            }
+        };
+        scan.directories.insert(path.to_path_buf(), modified);
+
+        // Entries are only added, removed or renamed along with a change of
+        // the directory's modification time, so the known files are still
+        // there. Files modified in place are picked up by a full rescan.
+        if scan.unchanged.get(path) == Some(&modified) {
+            for (_, resource) in children(scan.known, path) {
+                scan.reporter.visit(true);
+                scan.resources.push(resource.clone());
+            }
+
+            let unchanged = scan.unchanged;
+            for (directory, _) in children(unchanged, path) {
+                self.fetch_path(directory, scan);
+            }
+            return;
+        }
+
+        let mut entries: Vec<std::fs::DirEntry> = match std::fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok()).collect(),
+            Err(e) => {
+                log::error!("Cannot read {}: {}", path.display(), e);
+                scan.reporter.error(format!("{}: {}", path.display(), e));
+                return;
+            }
+        };
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                self.fetch_path(&path, scan);
+            } else {
+                let resource = self.resource(&path, scan.known);
+                scan.reporter.visit(resource.is_some());
+                scan.resources.extend(resource);
+            }
+        }
+    }
+
+    /// Creates a resource for the file at `path` if it has an allowed
+    /// extension or is a playlist and lies under one of the provider paths.
+    /// Known files are only read again if their size or modification time
+    /// changed.
+    fn resource(&self, path: &Path, known: &Files) -> Option<Resource> {
+        let stat = std::fs::metadata(path).ok()?;
+        if !stat.is_file() {
+            return None;
+        }
+
+        let allowed_extension = path.extension().is_some_and(|e| {
+            e.to_str()
+                .is_some_and(|e| self.extensions.iter().any(|x| x == e))
+        });
+        if !allowed_extension && !is_playlist(path) {
+            return None;
+        }
+
+        let (root, name) = self
+            .paths
+            .iter()
+            .enumerate()
+            .find_map(|(root, prefix)| Some((root, path.strip_prefix(prefix).ok()?)))?;
+        let name = name.to_str()?.to_string();
+        let uuid = file_resource_id(&self.provider, root, &name);
+
+        let size = Some(stat.len());
+        let modified = stat.modified().ok();
+        if let Some(known) = known.get(path) {
+            if modified.is_some()
+                && known.uuid == uuid
+                && known.size == size
+                && known.modified == modified
+            {
+                return Some(known.clone());
+            }
+        }
+
+        let media_type = classify(path);
+        let (metadata, entries) = if media_type == MediaType::Playlist {
+            (Default::default(), read_entries(path))
+        } else {
+            let metadata = read_metadata(path).unwrap_or_else(|e| {
+                log::debug!("No metadata for {}: {}", path.display(), e);
+                Default::default()
+            });
+            (metadata, Vec::new())
+        };
+
+        Some(Resource {
+            uuid,
+            provider: self.provider_id,
+            name,
+            location: Location::File(path.to_path_buf()),
+            media_type,
+            metadata,
+            entries,
+            resumable: false,
+            size,
+            modified,
+        })
+    }
+}
+
+fn read_entries(path: &Path) -> Vec<Location> {
+    match read_playlist(path) {
+        Ok(entries) => entries
+            .iter()
+            .map(|entry| resolve(path, &entry.location))
+            .collect(),
+        Err(e) => {
+            log::warn!("Cannot read playlist {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+type Files = BTreeMap<PathBuf, Resource>;
+
+/// Entries of `map` that lie directly in the directory `path`.
+fn children<'a, T>(
+    map: &'a BTreeMap<PathBuf, T>,
+    path: &'a Path,
+) -> impl Iterator<Item = (&'a PathBuf, &'a T)> {
+    map.range(path.to_path_buf()..)
+        .take_while(move |(entry, _)| entry.starts_with(path))
+        .filter(move |(entry, _)| entry.parent() == Some(path))
+}
+
+/// State of a scan, collecting the resources and directories found.
+struct Scan<'a> {
+    known: &'a Files,
+    /// Directories of the previous scan that are not read again if their
+    /// modification time did not change.
+    unchanged: &'a Directories,
+    reporter: &'a mut ScanReporter,
+    resources: Vec<Resource>,
+    directories: Directories,
+}
+
+/// Files known to the provider, used to translate filesystem events into
+/// resource updates.
+struct FileIndex {
+    scanner: Scanner,
+    files: Files,
+    /// Directories read by the latest scan.
+    directories: Directories,
+}
+
+impl FileIndex {
+    fn new(scanner: Scanner, resources: Vec<Resource>, directories: Directories) -> Self {
+        Self {
+            scanner,
+            files: to_files(resources),
+            directories,
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) -> Vec<ResourceProviderInterface> {
+        log::trace!("Filesystem event: {:?}", event);
+
+        let mut added = Vec::new();
+        let mut modified = Vec::new();
+        let mut removed = Vec::new();
+
+        match event.kind {
+            EventKind::Create(CreateKind::Folder) => {
+                event
+                    .paths
+                    .iter()
+                    .for_each(|path| added.extend(self.add_folder(path)));
+            }
+            // Editors and taggers save atomically by renaming a temporary file
+            // over the original one, which then shows up as created again.
+            EventKind::Create(_)
+            | EventKind::Modify(ModifyKind::Name(RenameMode::To))
+            | EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
+                for path in &event.paths {
+                    if !self.files.contains_key(path) {
+                        added.extend(self.add_path(path));
+                    } else if let Some(resource) = self.scanner.resource(path, &self.files) {
+                        self.files.insert(path.clone(), resource.clone());
+                        modified.push(resource);
                     }
                 }
-                Err(e) => log::error!("walkdir error: {:?}", e),
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+                event
+                    .paths
+                    .iter()
+                    .for_each(|path| removed.extend(self.remove_path(path)));
+            }
+            // A rename is also reported as `From` and `To`, which are handled
+            // above. The `Both` event that follows them is ignored, otherwise
+            // the renamed files would be added twice.
+            _ => {}
+        }
+
+        let mut messages = Vec::new();
+        if !removed.is_empty() {
+            messages.push(ResourceProviderInterface::Remove(removed));
+        }
+        if !added.is_empty() {
+            messages.push(ResourceProviderInterface::Add(added));
+        }
+        if !modified.is_empty() {
+            messages.push(ResourceProviderInterface::Modify(modified));
+        }
+
+        messages
+    }
+
+    /// Scans all provider paths again and reports the differences to the
+    /// files known so far. Unless `full` is set, directories that did not
+    /// change since the previous scan are not read again.
+    fn rescan(&mut self, mut reporter: ScanReporter, full: bool) -> Vec<ResourceProviderInterface> {
+        let unchanged = if full {
+            Directories::new()
+        } else {
+            std::mem::take(&mut self.directories)
+        };
+        let mut scan = Scan {
+            known: &self.files,
+            unchanged: &unchanged,
+            reporter: &mut reporter,
+            resources: Vec::new(),
+            directories: Directories::new(),
+        };
+        self.scanner.fetch(&mut scan);
+        let (resources, directories) = (scan.resources, scan.directories);
+        reporter.finish();
+
+        let found: HashSet<&Path> = resources.iter().filter_map(|res| res.file()).collect();
+        let mut removed: Vec<uuid::Uuid> = self
+            .files
+            .iter()
+            .filter(|(path, _)| !found.contains(path.as_path()))
+            .map(|(_, res)| res.uuid)
+            .collect();
+
+        let mut added = Vec::new();
+        let mut modified = Vec::new();
+        for res in &resources {
+            match res.file().and_then(|path| self.files.get(path)) {
+                None => added.push(res.clone()),
+                // The file lies under another root since the paths were reordered.
+                Some(known) if known.uuid != res.uuid => {
+                    removed.push(known.uuid);
+                    added.push(res.clone());
+                }
+                Some(known) if known.size != res.size || known.modified != res.modified => {
+                    modified.push(res.clone())
+                }
+                Some(_) => {}
+            }
+        }
+
+        log::info!(
+            "Scan of {} found {} new, {} modified and {} removed resources",
+            self.scanner.provider,
+            added.len(),
+            modified.len(),
+            removed.len()
+        );
+
+        self.files = to_files(resources);
+        self.directories = directories;
+
+        let mut messages = Vec::new();
+        if !removed.is_empty() {
+            messages.push(ResourceProviderInterface::Remove(removed));
+        }
+        if !added.is_empty() {
+            messages.push(ResourceProviderInterface::Add(added));
+        }
+        if !modified.is_empty() {
+            messages.push(ResourceProviderInterface::Modify(modified));
+        }
+        messages.push(ResourceProviderInterface::Directories {
+            provider: self.scanner.provider_id,
+            directories: ScannedDirectories {
+                paths: self.scanner.paths.clone(),
+                extensions: self.scanner.extensions.clone(),
+                modified: self.directories.clone(),
+            },
+        });
+
+        messages
+    }
+
+    fn add_path(&mut self, path: &Path) -> Vec<Resource> {
+        if path.is_dir() {
+            return self.add_folder(path);
+        }
+
+        let resource = self.scanner.resource(path, &self.files);
+        if let Some(resource) = &resource {
+            self.files.insert(path.to_path_buf(), resource.clone());
+        }
+
+        resource.into_iter().collect()
+    }
+
+    fn add_folder(&mut self, path: &Path) -> Vec<Resource> {
+        let mut scan = Scan {
+            known: &self.files,
+            unchanged: &Directories::new(),
+            reporter: &mut ScanReporter::silent(),
+            resources: Vec::new(),
+            directories: Directories::new(),
+        };
+        self.scanner.fetch_path(path, &mut scan);
+        let resources = scan.resources;
+        for resource in &resources {
+            if let Some(file) = resource.file() {
+                self.files.insert(file.to_path_buf(), resource.clone());
             }
         }
+
+        resources
     }
 
+    /// Removes the file at `path` or every file under it if it was a folder.
+    fn remove_path(&mut self, path: &Path) -> Vec<uuid::Uuid> {
+        let removed_paths: Vec<PathBuf> = self
+            .files
+            .range(path.to_path_buf()..)
+            .take_while(|(file, _)| file.starts_with(path))
+            .map(|(file, _)| file.clone())
+            .collect();
+
+        removed_paths
+            .iter()
+            .filter_map(|file| self.files.remove(file))
+            .map(|res| res.uuid)
+            .collect()
+    }
+}
+
+fn to_files(resources: Vec<Resource>) -> Files {
     resources
+        .into_iter()
+        .filter_map(|res| Some((res.file()?.to_path_buf(), res)))
+        .collect()
+}
+
+/// Counts visited files and periodically reports them to an optional listener.
+struct ScanReporter {
+    sender: Option<Sender<ScanProgress>>,
+    progress: ScanProgress,
+}
+
+impl ScanReporter {
+    fn new(provider: uuid::Uuid, sender: Sender<ScanProgress>) -> Self {
+        Self {
+            sender: Some(sender),
+            progress: ScanProgress {
+                provider,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn silent() -> Self {
+        Self {
+            sender: None,
+            progress: ScanProgress::default(),
+        }
+    }
+
+    fn visit(&mut self, found: bool) {
+        self.progress.files_visited += 1;
+        if found {
+            self.progress.resources_found += 1;
+        }
+
+        if self
+            .progress
+            .files_visited
+            .is_multiple_of(PROGRESS_INTERVAL)
+        {
+            self.report();
+        }
+    }
+
+    fn error(&mut self, error: String) {
+        if self.sender.is_some() {
+            self.progress.errors.push(error);
+        }
+    }
+
+    fn finish(mut self) {
+        self.progress.finished = true;
+        self.report();
+    }
+
+    fn report(&mut self) {
+        if let Some(sender) = &self.sender {
+            if sender.send(self.progress.clone()).is_err() {
+                self.sender = None;
+            }
+            self.progress.errors.clear();
+        }
+    }
 }
 
 #[async_trait]
 impl Provider for FilesystemProvider {
+    fn get_id(&self) -> uuid::Uuid {
+        self.id
+    }
+
     fn get_name(&self) -> &str {
         &self.name
     }
+
+    fn get_kind(&self) -> ProviderKind {
+        ProviderKind::Filesystem
+    }
+
+    fn get_roots(&self) -> Vec<String> {
+        self.paths.clone()
+    }
+
+    fn get_scan_status(&self) -> ScanStatus {
+        *self.status.borrow()
+    }
+
+    fn rescan(&self, progress: Sender<ScanProgress>) -> anyhow::Result<()> {
+        self.commands
+            .send(Command::Rescan(progress))
+            .map_err(|_| anyhow!("Provider {} is not running", self.name))
+    }
 }
@@ -1,16 +1,31 @@
+use crate::archive_provider::ArchiveProvider;
+use crate::fetcher::DefaultFetcher;
 use crate::filesystem_provider::FilesystemProvider;
-use crate::provider::{Provider, ResourceProviderInterface};
-use crate::resource::Resource;
+use crate::library;
+use crate::media_server::MEDIA_PATH;
+use crate::media_type::container_caps;
+use crate::playback::Playback;
+use crate::podcast_provider::PodcastProvider;
+use crate::positions::{load_positions, save_positions};
+use crate::provider::{Provider, ResourceProviderInterface, ScanProgress};
+use crate::queue::Queue;
+use crate::resource::{provider_id, Location, Resource};
+use crate::resource_index::{load_index, save_index, Index, ScannedDirectories};
+use crate::stream_provider::StreamProvider;
+use crate::user_playlists::{load_playlists, save_playlists, UserPlaylist};
 use crate::{
     config::{self, Config},
     ws_server::WebSocketHandler,
 };
-use anyhow::Result;
-use messages::sink_management::{SinkRequest, SinkResponse};
+use anyhow::{anyhow, Result};
+use messages::sink_management::{
+    AudioOutput, Capabilities, PlaybackSnapshot, SinkRequest, SinkResponse,
+};
 use messages::web_interface::{
-    self, Action, ProblemDetails, QueryProviders, QueryResources, WebUiRequest, WebUiResponse,
+    self, Action, Browse, EditPlaylist, MediaType, ProblemDetails, QueryProviders, QueryQueue,
+    QueryResources, Rescan, WebUiRequest, WebUiResponse,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::mpsc::{unbounded_channel as channel, UnboundedSender as Sender};
 use tokio::sync::RwLock;
 
@@ -18,32 +33,64 @@ type ResourceSender = Sender<ResourceProviderInterface>;
 type Providers = Vec<Box<dyn Provider + Sync + Send>>;
 type Resources = HashMap<uuid::Uuid, Resource>;
 type WebClients = HashMap<uuid::Uuid, Sender<WebUiResponse>>;
-type Sinks = HashMap<uuid::Uuid, Sink>;
+type Sinks = HashMap<uuid::Uuid, RegisteredSink>;
+type SinkConnections = HashMap<uuid::Uuid, SinkConnection>;
+type Queues = HashMap<uuid::Uuid, Queue>;
+type UserPlaylists = HashMap<uuid::Uuid, UserPlaylist>;
+type Positions = HashMap<uuid::Uuid, u64>;
 
-enum Sink {
-    Registered(RegisteredSink),
-    Unregistered(Sender<SinkResponse>),
-}
+/// Time to collect resource changes before the index is written.
+const INDEX_WRITE_DELAY: Duration = Duration::from_secs(5);
+/// Time to collect position updates before the positions are written.
+const POSITIONS_WRITE_DELAY: Duration = Duration::from_secs(10);
 
-impl Sink {
-    fn send_to_registered(&self, msg: SinkResponse) -> Result<()> {
-        match self {
-            Sink::Registered(sink) => Ok(sink.sender.send(msg)?),
-            Sink::Unregistered(_) => Ok(()),
-        }
-    }
+enum SinkConnection {
+    Registered(uuid::Uuid),
+    Unregistered(Sender<SinkResponse>),
 }
 
 struct RegisteredSink {
-    sender: Sender<SinkResponse>,
+    /// Sender of the live connection, `None` while the sink is disconnected.
+    sender: Option<Sender<SinkResponse>>,
     name: String,
+    capabilities: Capabilities,
+    playback: Playback,
+    volume: f64,
+    muted: bool,
+    outputs: Vec<AudioOutput>,
+    current_output: Option<String>,
+}
+
+impl RegisteredSink {
+    fn send(&mut self, msg: SinkResponse) -> Result<()> {
+        if let SinkResponse::Play { resource, .. } = &msg {
+            self.playback.start(*resource);
+        }
+
+        let sender = self
+            .sender
+            .as_ref()
+            .ok_or_else(|| anyhow!("Sink {} is offline", self.name))?;
+
+        sender
+            .send(msg)
+            .map_err(|e| anyhow!("Message could not be sent to sink {}: {}", self.name, e))
+    }
 }
 
 pub struct Hub {
     providers: RwLock<Providers>,
     resources: Arc<RwLock<Resources>>,
+    directories: Arc<RwLock<HashMap<uuid::Uuid, ScannedDirectories>>>,
     web_clients: Arc<RwLock<WebClients>>,
     sinks: Arc<RwLock<Sinks>>,
+    sink_connections: RwLock<SinkConnections>,
+    queues: Arc<RwLock<Queues>>,
+    playlists: RwLock<UserPlaylists>,
+    playlists_path: Option<PathBuf>,
+    positions: Arc<RwLock<Positions>>,
+    positions_writer: Option<Sender<()>>,
+    media_url: String,
 }
 
 impl Hub {
@@ -51,35 +98,181 @@ impl Hub {
         let mut zelf = Self {
             providers: RwLock::new(Providers::new()),
             resources: Arc::new(RwLock::new(Resources::new())),
+            directories: Arc::new(RwLock::new(HashMap::new())),
             web_clients: Arc::new(RwLock::new(WebClients::new())),
             sinks: Arc::new(RwLock::new(Sinks::new())),
+            sink_connections: RwLock::new(SinkConnections::new()),
+            queues: Arc::new(RwLock::new(Queues::new())),
+            playlists: RwLock::new(UserPlaylists::new()),
+            playlists_path: config.playlists_path.clone(),
+            positions: Arc::new(RwLock::new(Positions::new())),
+            positions_writer: None,
+            media_url: config.media_url(),
+        };
+
+        if let Some(path) = &config.positions_path {
+            match load_positions(path) {
+                Ok(positions) => zelf.positions = Arc::new(RwLock::new(positions)),
+                Err(e) => log::warn!("Cannot load playback positions {}: {}", path.display(), e),
+            }
+            zelf.positions_writer = Some(zelf.create_positions_writer(path.clone()));
+        }
+
+        if let Some(path) = &config.playlists_path {
+            match load_playlists(path) {
+                Ok(playlists) => {
+                    log::info!("Loaded {} playlists", playlists.len());
+                    zelf.playlists = RwLock::new(
+                        playlists
+                            .into_iter()
+                            .map(|playlist| (playlist.id, playlist))
+                            .collect(),
+                    );
+                }
+                Err(e) => log::warn!("Cannot load playlists {}: {}", path.display(), e),
+            }
+        }
+
+        let known = match &config.index_path {
+            Some(path) => load_index(path).unwrap_or_else(|e| {
+                log::warn!("Cannot load resource index {}: {}", path.display(), e);
+                Index::default()
+            }),
+            None => Index::default(),
         };
+        log::info!("Loaded {} resources from index", known.resources.len());
 
-        let (sender, _receiver_handle) = zelf.create_resource_receiver();
+        let index_writer = config
+            .index_path
+            .clone()
+            .map(|path| zelf.create_index_writer(path));
+        let (sender, _receiver_handle) = zelf.create_resource_receiver(index_writer);
 
-        let providers = create_providers(&config, sender);
+        let providers = create_providers(&config, sender, known.resources, known.directories);
         zelf.providers = RwLock::new(providers);
 
         zelf
     }
 
-    fn create_resource_receiver(&self) -> (ResourceSender, tokio::task::JoinHandle<()>) {
+    fn create_index_writer(&self, path: PathBuf) -> Sender<()> {
+        let (sender, mut receiver) = channel::<()>();
+
+        let resources = self.resources.clone();
+        let directories = self.directories.clone();
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                tokio::time::sleep(INDEX_WRITE_DELAY).await;
+                while receiver.try_recv().is_ok() {}
+
+                let snapshot = Index {
+                    resources: resources.read().await.values().cloned().collect(),
+                    directories: directories.read().await.clone(),
+                };
+                match save_index(&path, &snapshot).await {
+                    Ok(()) => log::debug!("Resource index written to {}", path.display()),
+                    Err(e) => log::error!("Cannot write resource index {}: {}", path.display(), e),
+                }
+            }
+        });
+
+        sender
+    }
+
+    fn create_positions_writer(&self, path: PathBuf) -> Sender<()> {
+        let (sender, mut receiver) = channel::<()>();
+
+        let positions = self.positions.clone();
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                tokio::time::sleep(POSITIONS_WRITE_DELAY).await;
+                while receiver.try_recv().is_ok() {}
+
+                let snapshot = positions.read().await.clone();
+                if let Err(e) = save_positions(&path, snapshot).await {
+                    log::error!("Cannot write playback positions {}: {}", path.display(), e);
+                }
+            }
+        });
+
+        sender
+    }
+
+    fn create_resource_receiver(
+        &self,
+        index_writer: Option<Sender<()>>,
+    ) -> (ResourceSender, tokio::task::JoinHandle<()>) {
         let (sender, mut receiver) = channel::<ResourceProviderInterface>();
 
         let resources = self.resources.clone();
+        let directories = self.directories.clone();
+        let queues = self.queues.clone();
+        let web_clients = self.web_clients.clone();
         let handle = tokio::spawn(async move {
             while let Some(msg) = receiver.recv().await {
-                let mut resources = resources.write().await;
-
-                match msg {
-                    ResourceProviderInterface::Add(new_resources) => {
-                        log::debug!("Received Add of {} resources", new_resources.len());
-                        new_resources.into_iter().for_each(|new| {
-                            let uid = uuid::Uuid::new_v4();
-                            resources.insert(uid, new);
-                        });
+                let mut changes = web_interface::ResourcesChanged {
+                    added: Vec::new(),
+                    removed: Vec::new(),
+                    modified: Vec::new(),
+                };
+
+                {
+                    let mut resources = resources.write().await;
+
+                    match msg {
+                        ResourceProviderInterface::Add(new_resources) => {
+                            log::debug!("Received Add of {} resources", new_resources.len());
+                            new_resources.into_iter().for_each(|new| {
+                                changes.added.push(new.to_web_interface());
+                                if let Some(old) = resources.insert(new.uuid, new) {
+                                    log::warn!("Resource {:?} was replaced", old);
+                                }
+                            });
+                        }
+                        ResourceProviderInterface::Remove(uids) => {
+                            log::debug!("Received Remove of {} resources", uids.len());
+                            changes.removed = uids
+                                .into_iter()
+                                .filter(|uid| resources.remove(uid).is_some())
+                                .collect();
+                        }
+                        ResourceProviderInterface::Modify(modified) => {
+                            log::debug!("Received Modify of {} resources", modified.len());
+                            modified.into_iter().for_each(|res| {
+                                changes.modified.push(res.to_web_interface());
+                                resources.insert(res.uuid, res);
+                            });
+                        }
+                        ResourceProviderInterface::Directories {
+                            provider,
+                            directories: scanned,
+                        } => {
+                            directories.write().await.insert(provider, scanned);
+                        }
+                    }
+                }
+
+                if !changes.removed.is_empty() {
+                    let removed = changes.removed.iter().copied().collect();
+                    for queue in queues.write().await.values_mut() {
+                        queue.remove_resources(&removed);
                     }
                 }
+
+                if let Some(index_writer) = &index_writer {
+                    index_writer.send(()).ok();
+                }
+
+                if changes.added.is_empty()
+                    && changes.removed.is_empty()
+                    && changes.modified.is_empty()
+                {
+                    continue;
+                }
+
+                notify_web_clients(
+                    &*web_clients.read().await,
+                    WebUiResponse::ResourcesChanged(changes),
+                );
             }
         });
 
@@ -90,12 +283,17 @@ impl Hub {
         let mut sinks = Vec::<web_interface::Sink>::new();
 
         for (uid, sink) in self.sinks.read().await.iter() {
-            if let Sink::Registered(sink) = sink {
-                sinks.push(web_interface::Sink {
-                    uid: *uid,
-                    name: sink.name.clone(),
-                });
-            }
+            sinks.push(web_interface::Sink {
+                uid: *uid,
+                name: sink.name.clone(),
+                online: sink.sender.is_some(),
+                capabilities: sink.capabilities.clone(),
+                playback: sink.playback.to_web_interface(),
+                volume: sink.volume,
+                muted: sink.muted,
+                outputs: sink.outputs.clone(),
+                current_output: sink.current_output.clone(),
+            });
         }
 
         WebUiResponse::Sinks(sinks)
@@ -108,88 +306,818 @@ impl Hub {
             });
         }
 
+        let mut counts = HashMap::<uuid::Uuid, usize>::new();
+        for res in self.resources.read().await.values() {
+            *counts.entry(res.provider).or_default() += 1;
+        }
+
         let providers = self.providers.read().await;
         let providers = providers
             .iter()
             .map(|v| web_interface::Provider {
+                id: v.get_id(),
                 name: v.get_name().to_string(),
+                kind: v.get_kind(),
+                roots: v.get_roots(),
+                resources: counts.get(&v.get_id()).copied().unwrap_or_default(),
+                scan_status: v.get_scan_status(),
             })
             .collect::<Vec<web_interface::Provider>>();
 
         WebUiResponse::Providers(providers.to_vec())
     }
 
-    async fn handle_query_resources(&self, _query: &QueryResources) -> WebUiResponse {
+    async fn handle_query_resources(&self, query: &QueryResources) -> WebUiResponse {
+        let resources = self.resources.read().await;
+        WebUiResponse::Resources(library::query(resources.values(), query))
+    }
+
+    async fn handle_browse(&self, browse: &Browse) -> WebUiResponse {
+        if let Browse::Folder { provider, .. } = browse {
+            let providers = self.providers.read().await;
+            if !providers.iter().any(|p| p.get_id() == *provider) {
+                return WebUiResponse::Error(ProblemDetails {
+                    description: format!("There is no provider identified by {}", provider),
+                });
+            }
+        }
+
         let resources = self.resources.read().await;
-        let resources = resources
+        if let Browse::Playlist { resource } = browse {
+            if resources
+                .get(resource)
+                .is_some_and(|res| res.media_type != MediaType::Playlist)
+            {
+                return WebUiResponse::Error(ProblemDetails {
+                    description: format!("Resource {} is not a playlist", resource),
+                });
+            }
+        }
+
+        match library::browse(&resources, browse) {
+            Ok(entries) => WebUiResponse::BrowseEntries(entries),
+            Err(e) => WebUiResponse::Error(ProblemDetails {
+                description: e.to_string(),
+            }),
+        }
+    }
+
+    async fn handle_rescan(&self, client: uuid::Uuid, rescan: &Rescan) -> WebUiResponse {
+        let client = match self.web_clients.read().await.get(&client) {
+            Some(client) => client.clone(),
+            None => {
+                return WebUiResponse::Error(ProblemDetails {
+                    description: format!("There is no web client identified by {}", client),
+                })
+            }
+        };
+
+        let providers = self.providers.read().await;
+        let providers: Vec<_> = providers
             .iter()
-            .map(|(uid, res)| web_interface::Resource {
-                uuid: *uid,
-                name: res.name.clone(),
-            })
+            .filter(|p| rescan.provider.is_none_or(|id| p.get_id() == id))
+            .collect();
+
+        if providers.is_empty() {
+            return WebUiResponse::Error(ProblemDetails {
+                description: match &rescan.provider {
+                    Some(id) => format!("There is no provider identified by {}", id),
+                    None => "There are no registered providers".into(),
+                },
+            });
+        }
+
+        // Providers that cannot rescan are reported as finished with an
+        // error, the others are scanning already.
+        let (progress_sender, mut progress_receiver) = channel::<ScanProgress>();
+        let mut failures = Vec::new();
+        for provider in &providers {
+            log::info!("Rescanning provider {}", provider.get_name());
+            if let Err(e) = provider.rescan(progress_sender.clone()) {
+                log::error!("Cannot rescan provider {}: {}", provider.get_name(), e);
+                failures.push(ScanProgress {
+                    provider: provider.get_id(),
+                    errors: vec![e.to_string()],
+                    finished: true,
+                    ..Default::default()
+                });
+            }
+        }
+
+        if failures.len() == providers.len() {
+            return WebUiResponse::Error(ProblemDetails {
+                description: failures
+                    .into_iter()
+                    .flat_map(|failure| failure.errors)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            });
+        }
+        for failure in failures {
+            progress_sender.send(failure).ok();
+        }
+
+        tokio::spawn(async move {
+            while let Some(progress) = progress_receiver.recv().await {
+                let progress = web_interface::ScanProgress {
+                    provider: progress.provider,
+                    files_visited: progress.files_visited,
+                    resources_found: progress.resources_found,
+                    errors: progress.errors,
+                    finished: progress.finished,
+                };
+
+                if client.send(WebUiResponse::ScanProgress(progress)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        WebUiResponse::Accepted
+    }
+
+    async fn handle_query_queue(&self, query: &QueryQueue) -> WebUiResponse {
+        if let Err(e) = find_registered_sink(&*self.sinks.read().await, &query.sink) {
+            return WebUiResponse::Error(e);
+        }
+
+        let queues = self.queues.read().await;
+        let resources = self.resources.read().await;
+
+        let queue = match queues.get(&query.sink) {
+            Some(queue) => web_interface::Queue {
+                sink: query.sink,
+                items: queue
+                    .items()
+                    .iter()
+                    .filter_map(|uid| resources.get(uid).map(|res| res.to_web_interface()))
+                    .collect(),
+                current: queue.current(),
+            },
+            None => web_interface::Queue {
+                sink: query.sink,
+                items: Vec::new(),
+                current: None,
+            },
+        };
+
+        WebUiResponse::Queue(queue)
+    }
+
+    async fn handle_query_playlists(&self) -> WebUiResponse {
+        let mut playlists: Vec<web_interface::Playlist> = self
+            .playlists
+            .read()
+            .await
+            .values()
+            .map(|playlist| playlist.to_web_interface())
             .collect();
+        playlists.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+        WebUiResponse::Playlists(playlists)
+    }
+
+    async fn handle_edit_playlist(&self, edit: &EditPlaylist) -> WebUiResponse {
+        if let Err(e) = self.apply_playlist_edit(edit).await {
+            return WebUiResponse::Error(e);
+        }
+
+        self.notify_web_clients(self.handle_query_playlists().await)
+            .await;
 
-        WebUiResponse::Resources(resources)
+        WebUiResponse::Accepted
     }
 
-    async fn handle_action(&self, query: &Action) -> WebUiResponse {
+    /// Changes the playlists and writes them to disk. The lock is held while
+    /// writing, so concurrent edits are persisted in order. The change is
+    /// dropped if the playlists cannot be written.
+    async fn apply_playlist_edit(&self, edit: &EditPlaylist) -> Result<(), ProblemDetails> {
+        let check_name = |name: &str| {
+            if name.trim().is_empty() {
+                return Err(ProblemDetails {
+                    description: "Playlist name must not be empty".into(),
+                });
+            }
+            Ok(name.trim().to_string())
+        };
+
+        if let EditPlaylist::Create { resources, .. }
+        | EditPlaylist::SetResources { resources, .. } = edit
         {
-            let sinks = self.sinks.read().await;
-            if sinks.is_empty() {
-                return WebUiResponse::Error(ProblemDetails {
-                    description: "There are no registered sinks".to_string(),
+            let known = self.resources.read().await;
+            if let Some(missing) = resources.iter().find(|uid| !known.contains_key(uid)) {
+                return Err(ProblemDetails {
+                    description: format!("Could not find a resource identified by {}", missing),
+                });
+            }
+        }
+
+        let mut current = self.playlists.write().await;
+        let mut playlists = current.clone();
+        match edit {
+            EditPlaylist::Create { name, resources } => {
+                let playlist = UserPlaylist {
+                    id: uuid::Uuid::new_v4(),
+                    name: check_name(name)?,
+                    resources: resources.clone(),
+                };
+                log::info!("Created playlist {} ({})", playlist.name, playlist.id);
+                playlists.insert(playlist.id, playlist);
+            }
+            EditPlaylist::Rename { playlist, name } => {
+                find_playlist_mut(&mut playlists, playlist)?.name = check_name(name)?;
+            }
+            EditPlaylist::SetResources {
+                playlist,
+                resources,
+            } => {
+                find_playlist_mut(&mut playlists, playlist)?.resources = resources.clone();
+            }
+            EditPlaylist::Delete { playlist } => {
+                find_playlist_mut(&mut playlists, playlist)?;
+                playlists.remove(playlist);
+            }
+        }
+
+        if let Some(path) = &self.playlists_path {
+            let snapshot = playlists.values().cloned().collect();
+            if let Err(e) = save_playlists(path, snapshot).await {
+                log::error!("Cannot write playlists {}: {}", path.display(), e);
+                return Err(ProblemDetails {
+                    description: format!("Playlists could not be saved: {}", e),
                 });
             }
         }
 
-        match query {
-            Action::Play(uid) => {
-                let resources = self.resources.read().await;
-                let resource = resources.get(uid);
+        *current = playlists;
+        Ok(())
+    }
+
+    /// Resources of a hub playlist that are currently part of the library.
+    async fn playlist_resources(
+        &self,
+        playlist: &uuid::Uuid,
+    ) -> Result<Vec<uuid::Uuid>, ProblemDetails> {
+        let resources = match self.playlists.read().await.get(playlist) {
+            Some(playlist) => playlist.resources.clone(),
+            None => {
+                return Err(ProblemDetails {
+                    description: format!("There is no playlist identified by {}", playlist),
+                })
+            }
+        };
+
+        let known = self.resources.read().await;
+        let resources: Vec<uuid::Uuid> = resources
+            .into_iter()
+            .filter(|uid| known.contains_key(uid))
+            .collect();
+
+        if resources.is_empty() {
+            return Err(ProblemDetails {
+                description: format!("Playlist {} lists no resources of the library", playlist),
+            });
+        }
+
+        Ok(resources)
+    }
+
+    /// Carries out the action. Web clients are only told about the playback
+    /// if the action changed it, other changes follow once the sink reports
+    /// them.
+    async fn handle_action(&self, action: &Action) -> WebUiResponse {
+        let previous = self.playback(action.sink()).await;
+        let response = self.dispatch_action(action).await;
+        if let WebUiResponse::Accepted = response {
+            if self.playback(action.sink()).await != previous {
+                self.notify_now_playing(action.sink()).await;
+            }
+        }
+
+        response
+    }
+
+    async fn playback(&self, sink_uid: uuid::Uuid) -> Option<Playback> {
+        self.sinks
+            .read()
+            .await
+            .get(&sink_uid)
+            .map(|sink| sink.playback.clone())
+    }
+
+    async fn dispatch_action(&self, action: &Action) -> WebUiResponse {
+        let mut sinks = self.sinks.write().await;
+        let sink = match find_online_sink_mut(&mut sinks, &action.sink()) {
+            Ok(sink) => sink,
+            Err(e) => return WebUiResponse::Error(e),
+        };
 
-                match resource {
-                    Some(r) => {
-                        log::debug!("Received action for resource {:?}", r);
-                        let msg = SinkResponse::Play(r.path.clone());
+        if let Err(e) = self.check_capabilities(sink, action).await {
+            return WebUiResponse::Error(e);
+        }
+
+        let msg = match self.apply_action(action).await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => return WebUiResponse::Accepted,
+            Err(e) => return WebUiResponse::Error(e),
+        };
 
-                        let sinks = self.sinks.read().await;
-                        let (_uid, sink) = sinks.iter().next().unwrap();
-                        sink.send_to_registered(msg).expect("Message sent to sink");
+        match sink.send(msg) {
+            Ok(()) => WebUiResponse::Accepted,
+            Err(e) => WebUiResponse::Error(ProblemDetails {
+                description: e.to_string(),
+            }),
+        }
+    }
+
+    /// Refuses actions the sink announced no support for.
+    async fn check_capabilities(
+        &self,
+        sink: &RegisteredSink,
+        action: &Action,
+    ) -> Result<(), ProblemDetails> {
+        let capabilities = &sink.capabilities;
+        let unsupported = |control: &str| ProblemDetails {
+            description: format!("Sink {} does not support {}", sink.name, control),
+        };
+
+        match action {
+            Action::Play { resource, .. } => {
+                self.check_playable(sink, std::slice::from_ref(resource))
+                    .await
+            }
+            Action::Enqueue { resources, .. } => self.check_playable(sink, resources).await,
+            Action::PlayPlaylist { playlist, .. } | Action::EnqueuePlaylist { playlist, .. } => {
+                let resources = self.playlist_resources(playlist).await?;
+                self.check_playable(sink, &resources).await
+            }
+            Action::Seek { .. } if !capabilities.seekable => Err(unsupported("seeking")),
+            Action::SetVolume { .. } | Action::SetMute { .. } if !capabilities.volume => {
+                Err(unsupported("volume control"))
+            }
+            Action::SetOutput { .. } if !capabilities.output_selection => {
+                Err(unsupported("output selection"))
+            }
+            Action::SetOutput { output, .. } if !sink.outputs.iter().any(|o| &o.name == output) => {
+                Err(ProblemDetails {
+                    description: format!("Sink {} has no output named {}", sink.name, output),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuses resources the sink cannot play, playlists are checked entry
+    /// by entry.
+    async fn check_playable(
+        &self,
+        sink: &RegisteredSink,
+        uids: &[uuid::Uuid],
+    ) -> Result<(), ProblemDetails> {
+        let capabilities = &sink.capabilities;
+        let uids = self.expand(uids).await?;
+        let resources = self.resources.read().await;
+
+        for res in uids.iter().filter_map(|uid| resources.get(uid)) {
+            let supported = match res.media_type {
+                MediaType::Audio => capabilities.audio,
+                MediaType::Video | MediaType::Image => capabilities.video,
+                MediaType::Playlist => false,
+                MediaType::Unknown => {
+                    log::warn!("Sink {} may not be able to play {}", sink.name, res.name);
+                    true
+                }
+            };
+
+            let container = res.content_path().and_then(container_caps);
+            let supported = supported
+                && container.is_none_or(|caps| {
+                    capabilities.containers.is_empty()
+                        || capabilities.containers.iter().any(|c| c == caps)
+                });
+
+            if !supported {
+                return Err(ProblemDetails {
+                    description: format!(
+                        "Sink {} cannot play {} ({:?})",
+                        sink.name, res.name, res.media_type
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Updates the hub state for the action and returns the message that
+    /// has to be sent to the sink, if any.
+    async fn apply_action(&self, action: &Action) -> Result<Option<SinkResponse>, ProblemDetails> {
+        let mut queues = self.queues.write().await;
+        let queue = queues.entry(action.sink()).or_default();
+
+        match action {
+            Action::Play { resource, .. } => {
+                let resources = self.expand(std::slice::from_ref(resource)).await?;
+                let msg = self.create_play_message(&resources[0]).await?;
+                queue.play_now(&resources);
+                Ok(Some(msg))
+            }
+            Action::Stop { .. } => Ok(Some(SinkResponse::Stop)),
+            Action::Pause { .. } => Ok(Some(SinkResponse::Pause)),
+            Action::Enqueue { resources, .. } => {
+                queue.enqueue(self.expand(resources).await?);
+                Ok(None)
+            }
+            Action::PlayPlaylist { playlist, .. } => {
+                let resources = self.playlist_resources(playlist).await?;
+                let resources = self.expand(&resources).await?;
+                let msg = self.create_play_message(&resources[0]).await?;
+                queue.play_now(&resources);
+                Ok(Some(msg))
+            }
+            Action::EnqueuePlaylist { playlist, .. } => {
+                let resources = self.playlist_resources(playlist).await?;
+                queue.enqueue(self.expand(&resources).await?);
+                Ok(None)
+            }
+            Action::Next { .. } => match queue.next() {
+                Some(resource) => Ok(Some(self.create_play_message(&resource).await?)),
+                None => Err(ProblemDetails {
+                    description: "There is no next resource in the queue".into(),
+                }),
+            },
+            Action::Previous { .. } => match queue.previous() {
+                Some(resource) => Ok(Some(self.create_play_message(&resource).await?)),
+                None => Err(ProblemDetails {
+                    description: "There is no previous resource in the queue".into(),
+                }),
+            },
+            Action::ClearQueue { .. } => {
+                queue.clear();
+                Ok(None)
+            }
+            Action::RemoveFromQueue { index, .. } => {
+                queue
+                    .remove(*index)
+                    .map(|_| None)
+                    .map_err(|e| ProblemDetails {
+                        description: e.to_string(),
+                    })
+            }
+            Action::MoveQueueItem { from, to, .. } => queue
+                .move_item(*from, *to)
+                .map(|_| None)
+                .map_err(|e| ProblemDetails {
+                    description: e.to_string(),
+                }),
+            Action::Seek { position, .. } => Ok(Some(SinkResponse::Seek(*position))),
+            Action::SetVolume { volume, .. } => {
+                if !(0.0..=1.0).contains(volume) {
+                    return Err(ProblemDetails {
+                        description: format!("Volume {} is outside of the 0.0 - 1.0 range", volume),
+                    });
+                }
+                Ok(Some(SinkResponse::SetVolume(*volume)))
+            }
+            Action::SetMute { mute, .. } => Ok(Some(SinkResponse::SetMute(*mute))),
+            Action::SetOutput { output, .. } => Ok(Some(SinkResponse::SetOutput(output.clone()))),
+        }
+    }
+
+    /// Resolves playlists to the resources they list.
+    async fn expand(&self, uids: &[uuid::Uuid]) -> Result<Vec<uuid::Uuid>, ProblemDetails> {
+        library::expand(&*self.resources.read().await, uids).map_err(|e| ProblemDetails {
+            description: e.to_string(),
+        })
+    }
+
+    /// Location of the resource, used by the media server.
+    pub async fn resource_location(&self, uid: &uuid::Uuid) -> Option<Location> {
+        self.resources
+            .read()
+            .await
+            .get(uid)
+            .map(|res| res.location.clone())
+    }
+
+    async fn create_play_message(&self, uid: &uuid::Uuid) -> Result<SinkResponse, ProblemDetails> {
+        let resources = self.resources.read().await;
+        match resources.get(uid) {
+            Some(r) => {
+                log::debug!("Playing resource {:?}", r);
+                let url = match &r.location {
+                    Location::File(_) | Location::Archive { .. } => {
+                        format!("{}{}{}", self.media_url, MEDIA_PATH, uid)
                     }
-                    None => {
-                        log::debug!("Could not find a resource identified by {}", uid);
+                    Location::Url(url) => url.clone(),
+                };
+                let start_ms = if r.resumable {
+                    self.positions.read().await.get(uid).copied()
+                } else {
+                    None
+                };
+                Ok(SinkResponse::Play {
+                    resource: *uid,
+                    url,
+                    start_ms,
+                })
+            }
+            None => Err(ProblemDetails {
+                description: format!("Could not find a resource identified by {}", uid),
+            }),
+        }
+    }
+
+    async fn playing_resource(&self, sink_uid: uuid::Uuid) -> Option<uuid::Uuid> {
+        self.sinks
+            .read()
+            .await
+            .get(&sink_uid)
+            .and_then(|sink| sink.playback.resource)
+    }
+
+    /// Remembers where playback of a resumable resource was left off,
+    /// `None` forgets the position once it played to the end.
+    async fn remember_position(&self, resource: uuid::Uuid, position_ms: Option<u64>) {
+        let resumable = self
+            .resources
+            .read()
+            .await
+            .get(&resource)
+            .is_some_and(|res| res.resumable);
+        if !resumable {
+            return;
+        }
+
+        {
+            let mut positions = self.positions.write().await;
+            match position_ms {
+                Some(position_ms) => positions.insert(resource, position_ms),
+                None => positions.remove(&resource),
+            };
+        }
+
+        if let Some(writer) = &self.positions_writer {
+            writer.send(()).ok();
+        }
+    }
+
+    async fn advance_queue(&self, sink_uid: uuid::Uuid) {
+        let next = match self.queues.write().await.get_mut(&sink_uid) {
+            Some(queue) => queue.next(),
+            None => None,
+        };
+
+        let resource = match next {
+            Some(resource) => resource,
+            None => {
+                log::debug!("Queue of sink {} finished", sink_uid);
+                return;
+            }
+        };
+
+        let msg = match self.create_play_message(&resource).await {
+            Ok(msg) => msg,
+            Err(e) => {
+                log::warn!(
+                    "Could not advance queue of sink {}: {}",
+                    sink_uid,
+                    e.description
+                );
+                return;
+            }
+        };
+
+        {
+            let mut sinks = self.sinks.write().await;
+            match find_online_sink_mut(&mut sinks, &sink_uid) {
+                Ok(sink) => {
+                    if let Err(e) = sink.send(msg) {
+                        log::warn!("{}", e);
                     }
                 }
+                Err(e) => log::warn!("Could not advance queue: {}", e.description),
+            }
+        }
+
+        self.notify_now_playing(sink_uid).await;
+    }
+
+    async fn update_playback<F>(&self, sink_uid: uuid::Uuid, update: F)
+    where
+        F: FnOnce(&mut Playback),
+    {
+        let changed = {
+            let mut sinks = self.sinks.write().await;
+            match find_online_sink_mut(&mut sinks, &sink_uid) {
+                Ok(sink) => {
+                    let previous = sink.playback.clone();
+                    update(&mut sink.playback);
+                    previous != sink.playback
+                }
+                Err(e) => {
+                    log::warn!("Could not update playback state: {}", e.description);
+                    false
+                }
+            }
+        };
+
+        if changed {
+            self.notify_now_playing(sink_uid).await;
+        }
+    }
+
+    /// Applies `update` to a registered sink and notifies web clients about
+    /// the new sink list if the update reported a change.
+    async fn update_sink<F>(&self, sink_uid: uuid::Uuid, update: F)
+    where
+        F: FnOnce(&mut RegisteredSink) -> bool,
+    {
+        let changed = {
+            let mut sinks = self.sinks.write().await;
+            match find_online_sink_mut(&mut sinks, &sink_uid) {
+                Ok(sink) => update(sink),
+                Err(e) => {
+                    log::warn!("Could not update sink: {}", e.description);
+                    false
+                }
+            }
+        };
+
+        if changed {
+            self.notify_web_clients(self.handle_query_sinks().await)
+                .await;
+        }
+    }
+
+    async fn notify_now_playing(&self, sink_uid: uuid::Uuid) {
+        let now_playing = {
+            let sinks = self.sinks.read().await;
+            let playback = match find_registered_sink(&sinks, &sink_uid) {
+                Ok(sink) => &sink.playback,
+                Err(_) => return,
+            };
+
+            let resources = self.resources.read().await;
+            let resource = playback
+                .resource
+                .and_then(|uid| resources.get(&uid).map(|res| res.to_web_interface()));
+
+            web_interface::NowPlaying {
+                sink: sink_uid,
+                resource,
+                state: playback.state,
+                position_ms: playback.position_ms,
+                duration_ms: playback.duration_ms,
+                stream_title: playback.stream_title.clone(),
+            }
+        };
+
+        self.notify_web_clients(WebUiResponse::NowPlaying(Box::new(now_playing)))
+            .await;
+    }
+
+    async fn register_sink(
+        &self,
+        connection_id: uuid::Uuid,
+        sink_id: uuid::Uuid,
+        name: String,
+        capabilities: Capabilities,
+        playback: Option<PlaybackSnapshot>,
+    ) -> SinkResponse {
+        let sender = match self.sink_connections.read().await.get(&connection_id) {
+            Some(SinkConnection::Unregistered(sender)) => sender.clone(),
+            Some(SinkConnection::Registered(registered_id)) => {
+                return SinkResponse::RegistrationRejected(format!(
+                    "Connection is already registered as sink {}",
+                    registered_id
+                ));
+            }
+            None => {
+                log::warn!("Register from unknown sink connection {}", connection_id);
+                return SinkResponse::Dummy;
             }
-            Action::Stop => {
-                let msg = SinkResponse::Stop;
-                let sinks = self.sinks.read().await;
-                let (_uid, sink) = sinks.iter().next().unwrap();
-                sink.send_to_registered(msg).expect("Message sent to sink");
+        };
+
+        {
+            let mut sinks = self.sinks.write().await;
+            match sinks.get_mut(&sink_id) {
+                // Connections of sinks that lost power or network are closed
+                // by the websocket server once they stop answering pings.
+                Some(sink) if sink.sender.as_ref().is_some_and(|s| !s.is_closed()) => {
+                    log::warn!(
+                        "Sink {} is already connected, rejecting registration",
+                        sink_id
+                    );
+                    return SinkResponse::RegistrationRejected(format!(
+                        "Sink {} is already connected",
+                        sink_id
+                    ));
+                }
+                Some(sink) => {
+                    log::debug!("Sink {} reconnected as {}", sink_id, name);
+                    sink.sender = Some(sender);
+                    sink.name = name;
+                    sink.capabilities = capabilities;
+                    sink.playback = playback
+                        .clone()
+                        .map(Playback::from_snapshot)
+                        .unwrap_or_default();
+                }
+                None => {
+                    log::debug!("Registering new sink {} as {}", sink_id, name);
+                    sinks.insert(
+                        sink_id,
+                        RegisteredSink {
+                            sender: Some(sender),
+                            name,
+                            capabilities,
+                            playback: playback
+                                .clone()
+                                .map(Playback::from_snapshot)
+                                .unwrap_or_default(),
+                            volume: 1.0,
+                            muted: false,
+                            outputs: Vec::new(),
+                            current_output: None,
+                        },
+                    );
+                }
             }
-            Action::Pause => {
-                let msg = SinkResponse::Pause;
-                let sinks = self.sinks.read().await;
-                let (_uid, sink) = sinks.iter().next().unwrap();
-                sink.send_to_registered(msg).expect("Message sent to sink");
+        }
+
+        self.sink_connections
+            .write()
+            .await
+            .insert(connection_id, SinkConnection::Registered(sink_id));
+
+        if let Some(snapshot) = &playback {
+            log::debug!("Sink {} restores playback {:?}", sink_id, snapshot);
+            let mut queues = self.queues.write().await;
+            let queue = queues.entry(sink_id).or_default();
+            if queue.current_resource() != Some(snapshot.resource)
+                && self.resources.read().await.contains_key(&snapshot.resource)
+            {
+                queue.play_now(&[snapshot.resource]);
             }
         }
 
-        WebUiResponse::Error(ProblemDetails {
-            description: "Action not implemented".into(),
-        })
+        self.notify_web_clients(self.handle_query_sinks().await)
+            .await;
+
+        SinkResponse::Registered
     }
 
     async fn notify_web_clients(&self, msg: WebUiResponse) {
-        let web_clients = self.web_clients.read().await;
+        notify_web_clients(&*self.web_clients.read().await, msg);
+    }
+}
 
-        for (_, client) in web_clients.iter() {
-            client.send(msg.clone()).expect("Message sent to WebClient");
-        }
+fn notify_web_clients(web_clients: &WebClients, msg: WebUiResponse) {
+    for (_, client) in web_clients.iter() {
+        client.send(msg.clone()).expect("Message sent to WebClient");
+    }
+}
+
+fn find_registered_sink<'a>(
+    sinks: &'a Sinks,
+    uid: &uuid::Uuid,
+) -> Result<&'a RegisteredSink, ProblemDetails> {
+    sinks.get(uid).ok_or_else(|| ProblemDetails {
+        description: format!("There is no sink identified by {}", uid),
+    })
+}
+
+fn find_online_sink_mut<'a>(
+    sinks: &'a mut Sinks,
+    uid: &uuid::Uuid,
+) -> Result<&'a mut RegisteredSink, ProblemDetails> {
+    match sinks.get_mut(uid) {
+        Some(sink) if sink.sender.is_some() => Ok(sink),
+        Some(sink) => Err(ProblemDetails {
+            description: format!("Sink {} is offline", sink.name),
+        }),
+        None => Err(ProblemDetails {
+            description: format!("There is no sink identified by {}", uid),
+        }),
     }
 }
 
-fn create_providers(config: &Config, resource_sender: ResourceSender) -> Providers {
+fn find_playlist_mut<'a>(
+    playlists: &'a mut UserPlaylists,
+    id: &uuid::Uuid,
+) -> Result<&'a mut UserPlaylist, ProblemDetails> {
+    playlists.get_mut(id).ok_or_else(|| ProblemDetails {
+        description: format!("There is no playlist identified by {}", id),
+    })
+}
+
+fn create_providers(
+    config: &Config,
+    resource_sender: ResourceSender,
+    known: Vec<Resource>,
+    mut directories: HashMap<uuid::Uuid, ScannedDirectories>,
+) -> Providers {
     let mut providers = Providers::new();
 
     for provider in &config.providers {
@@ -199,10 +1127,56 @@ fn create_providers(config: &Config, resource_sender: ResourceSender) -> Provide
                     p.name.clone(),
                     p.paths.clone(),
                     p.extensions.clone(),
+                    known
+                        .iter()
+                        .filter(|res| res.provider == provider_id(&p.name))
+                        .cloned()
+                        .collect(),
+                    directories
+                        .remove(&provider_id(&p.name))
+                        .unwrap_or_default(),
                     resource_sender.clone(),
                 );
                 providers.push(Box::new(fs_provider));
             }
+            config::Provider::Stream(p) => {
+                let stream_provider = StreamProvider::new(
+                    p.name.clone(),
+                    p.stations.clone(),
+                    p.playlist.clone(),
+                    resource_sender.clone(),
+                );
+                providers.push(Box::new(stream_provider));
+            }
+            config::Provider::Podcast(p) => {
+                let podcast_provider = PodcastProvider::new(
+                    p.name.clone(),
+                    p.feeds.clone(),
+                    p.download_path.clone(),
+                    Arc::new(DefaultFetcher::default()),
+                    known
+                        .iter()
+                        .filter(|res| res.provider == provider_id(&p.name))
+                        .cloned()
+                        .collect(),
+                    resource_sender.clone(),
+                );
+                providers.push(Box::new(podcast_provider));
+            }
+            config::Provider::Archive(p) => {
+                let archive_provider = ArchiveProvider::new(
+                    p.name.clone(),
+                    p.paths.clone(),
+                    p.extensions.clone(),
+                    known
+                        .iter()
+                        .filter(|res| res.provider == provider_id(&p.name))
+                        .cloned()
+                        .collect(),
+                    resource_sender.clone(),
+                );
+                providers.push(Box::new(archive_provider));
+            }
         }
     }
 
@@ -211,12 +1185,17 @@ fn create_providers(config: &Config, resource_sender: ResourceSender) -> Provide
 
 #[async_trait::async_trait]
 impl WebSocketHandler<WebUiRequest, WebUiResponse> for Hub {
-    async fn handle(&self, _: uuid::Uuid, req: WebUiRequest) -> WebUiResponse {
+    async fn handle(&self, id: uuid::Uuid, req: WebUiRequest) -> WebUiResponse {
         match req {
             WebUiRequest::QuerySinks => self.handle_query_sinks().await,
             WebUiRequest::QueryProviders(q) => self.handle_query_providers(&q).await,
             WebUiRequest::QueryResources(q) => self.handle_query_resources(&q).await,
+            WebUiRequest::QueryQueue(q) => self.handle_query_queue(&q).await,
             WebUiRequest::Action(q) => self.handle_action(&q).await,
+            WebUiRequest::Rescan(q) => self.handle_rescan(id, &q).await,
+            WebUiRequest::Browse(q) => self.handle_browse(&q).await,
+            WebUiRequest::QueryPlaylists => self.handle_query_playlists().await,
+            WebUiRequest::EditPlaylist(q) => self.handle_edit_playlist(&q).await,
         }
     }
 
@@ -236,35 +1215,107 @@ impl WebSocketHandler<WebUiRequest, WebUiResponse> for Hub {
 #[async_trait::async_trait]
 impl WebSocketHandler<SinkRequest, SinkResponse> for Hub {
     async fn handle(&self, id: uuid::Uuid, req: SinkRequest) -> SinkResponse {
-        match req {
-            SinkRequest::Register { name } => {
-                let mut needs_notification = false;
+        if let SinkRequest::Register {
+            id: sink_id,
+            name,
+            capabilities,
+            playback,
+        } = req
+        {
+            return self
+                .register_sink(id, sink_id, name, capabilities, playback)
+                .await;
+        }
 
-                {
-                    let mut sink_lock = self.sinks.write().await;
-                    let sink = sink_lock.get_mut(&id);
-                    if let Some(sink) = sink {
-                        match sink {
-                            Sink::Registered(_) => {
-                                // TODO: Incorrect state, disconnect
-                                todo!()
-                            }
-                            Sink::Unregistered(sender) => {
-                                *sink = Sink::Registered(RegisteredSink {
-                                    sender: sender.clone(),
-                                    name,
-                                });
-                                needs_notification = true;
-                            }
-                        }
-                    }
+        let sink_id = match self.sink_connections.read().await.get(&id) {
+            Some(SinkConnection::Registered(sink_id)) => *sink_id,
+            _ => {
+                log::warn!(
+                    "Ignoring {:?} from unregistered sink connection {}",
+                    req,
+                    id
+                );
+                return SinkResponse::Dummy;
+            }
+        };
+
+        match req {
+            SinkRequest::Register { .. } => {}
+            SinkRequest::EndOfStream => {
+                log::debug!("Sink {} reached end of stream", sink_id);
+                if let Some(resource) = self.playing_resource(sink_id).await {
+                    self.remember_position(resource, None).await;
+                }
+                self.update_playback(sink_id, Playback::finish).await;
+                self.advance_queue(sink_id).await;
+            }
+            SinkRequest::StateChanged(state) => {
+                self.update_playback(sink_id, |playback| playback.set_state(state))
+                    .await;
+            }
+            SinkRequest::PositionChanged {
+                resource,
+                position_ms,
+                duration_ms,
+            } => {
+                let playing = self.playing_resource(sink_id).await;
+                if resource.is_some() && resource != playing {
+                    log::debug!(
+                        "Ignoring position of {:?} from sink {}, it plays {:?}",
+                        resource,
+                        sink_id,
+                        playing
+                    );
+                    return SinkResponse::Dummy;
                 }
 
-                if needs_notification {
-                    self.notify_web_clients(self.handle_query_sinks().await)
-                        .await;
+                self.update_playback(sink_id, |playback| {
+                    playback.set_position(position_ms, duration_ms)
+                })
+                .await;
+                if let Some(playing) = playing {
+                    self.remember_position(playing, Some(position_ms)).await;
                 }
             }
+            SinkRequest::Buffering(percent) => {
+                self.update_playback(sink_id, |playback| playback.set_buffering(percent))
+                    .await;
+            }
+            SinkRequest::Error(error) => {
+                log::warn!("Sink {} reported an error: {}", sink_id, error);
+                self.update_playback(sink_id, |playback| playback.set_error(error))
+                    .await;
+            }
+            SinkRequest::TitleChanged(title) => {
+                log::debug!("Sink {} plays {}", sink_id, title);
+                self.update_playback(sink_id, |playback| playback.set_stream_title(title))
+                    .await;
+            }
+            SinkRequest::VolumeChanged { volume, muted } => {
+                self.update_sink(sink_id, |sink| {
+                    let changed = sink.volume != volume || sink.muted != muted;
+                    sink.volume = volume;
+                    sink.muted = muted;
+                    changed
+                })
+                .await;
+            }
+            SinkRequest::OutputsChanged(outputs) => {
+                self.update_sink(sink_id, |sink| {
+                    let changed = sink.outputs != outputs;
+                    sink.outputs = outputs;
+                    changed
+                })
+                .await;
+            }
+            SinkRequest::OutputChanged(output) => {
+                self.update_sink(sink_id, |sink| {
+                    let changed = sink.current_output.as_ref() != Some(&output);
+                    sink.current_output = Some(output);
+                    changed
+                })
+                .await;
+            }
         }
 
         SinkResponse::Dummy
@@ -272,16 +1323,27 @@ impl WebSocketHandler<SinkRequest, SinkResponse> for Hub {
 
     async fn add_connection(&self, sender: Sender<SinkResponse>) -> uuid::Uuid {
         let uid = uuid::Uuid::new_v4();
-        log::debug!("Adding new sink {}", uid);
-        self.sinks
+        log::debug!("Adding new sink connection {}", uid);
+        self.sink_connections
             .write()
             .await
-            .insert(uid, Sink::Unregistered(sender));
+            .insert(uid, SinkConnection::Unregistered(sender));
         uid
     }
 
     async fn remove_connection(&self, uid: uuid::Uuid) {
-        log::debug!("Removing sink: {}", uid);
-        self.sinks.write().await.remove(&uid);
+        log::debug!("Removing sink connection: {}", uid);
+        let connection = self.sink_connections.write().await.remove(&uid);
+
+        if let Some(SinkConnection::Registered(sink_id)) = connection {
+            if let Some(sink) = self.sinks.write().await.get_mut(&sink_id) {
+                log::debug!("Sink {} went offline", sink_id);
+                sink.sender = None;
+                sink.playback.disconnect();
+            }
+
+            self.notify_web_clients(self.handle_query_sinks().await)
+                .await;
+        }
     }
 }
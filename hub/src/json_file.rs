@@ -0,0 +1,34 @@
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+/// Reads a JSON document, e.g. one written by [`save_atomic`].
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T> {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    serde_json::from_reader(reader).map_err(|e| anyhow!("{:?}", e))
+}
+
+/// Writes `value` as JSON to a temporary file first and moves it into place
+/// once it is on disk, so an interrupted write or a crash does not destroy
+/// the previous content.
+pub async fn save_atomic<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let path = path.as_ref();
+    let content = serde_json::to_vec(value)?;
+
+    let temporary = path.with_extension("tmp");
+    let mut file = tokio::fs::File::create(&temporary).await?;
+    file.write_all(&content).await?;
+    file.sync_all().await?;
+    drop(file);
+    tokio::fs::rename(&temporary, path).await?;
+
+    Ok(())
+}
@@ -0,0 +1,366 @@
+use crate::resource::{Location, Resource};
+use anyhow::{anyhow, Result};
+use messages::web_interface::{
+    Browse, BrowseEntry, MediaType, Metadata, QueryResources, ResourceList, SortKey,
+};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Component, Path};
+
+/// Filters, sorts and paginates the resources as requested by `query`.
+pub fn query<'a>(
+    resources: impl Iterator<Item = &'a Resource>,
+    query: &QueryResources,
+) -> ResourceList {
+    let terms: Vec<String> = query
+        .search
+        .iter()
+        .flat_map(|search| search.split_whitespace())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut resources: Vec<&Resource> = resources
+        .filter(|res| {
+            query
+                .provider
+                .is_none_or(|provider| res.provider == provider)
+        })
+        .filter(|res| has_extension(res, &query.extensions))
+        .filter(|res| query.media_types.is_empty() || query.media_types.contains(&res.media_type))
+        .filter(|res| terms.iter().all(|term| contains_term(res, term)))
+        .collect();
+
+    resources.sort_by(|a, b| {
+        let ordering = compare(a, b, query.sort).then_with(|| a.name.cmp(&b.name));
+        if query.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    let total = resources.len();
+    let resources = resources
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .map(|res| res.to_web_interface())
+        .collect();
+
+    ResourceList {
+        total,
+        offset: query.offset,
+        resources,
+    }
+}
+
+fn has_extension(resource: &Resource, extensions: &[String]) -> bool {
+    if extensions.is_empty() {
+        return true;
+    }
+
+    resource
+        .content_path()
+        .and_then(|path| path.extension())
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|ext| ext.eq_ignore_ascii_case(e)))
+}
+
+/// Checks whether the lowercase `term` appears in the name or the tags.
+fn contains_term(resource: &Resource, term: &str) -> bool {
+    let metadata = &resource.metadata;
+    std::iter::once(Some(&resource.name))
+        .chain([
+            metadata.title.as_ref(),
+            metadata.artist.as_ref(),
+            metadata.album.as_ref(),
+            metadata.album_artist.as_ref(),
+            metadata.genre.as_ref(),
+        ])
+        .flatten()
+        .any(|field| field.to_lowercase().contains(term))
+}
+
+/// Orders by the sort key, resources without the value go last.
+fn compare(a: &Resource, b: &Resource, key: SortKey) -> Ordering {
+    fn some_first<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    let (a, b) = (&a.metadata, &b.metadata);
+    match key {
+        SortKey::Name => Ordering::Equal,
+        SortKey::Title => some_first(a.title.as_ref(), b.title.as_ref()),
+        SortKey::Artist => some_first(album_artist(a), album_artist(b)),
+        SortKey::Album => some_first(a.album.as_ref(), b.album.as_ref())
+            .then_with(|| some_first(a.disc_number, b.disc_number))
+            .then_with(|| some_first(a.track_number, b.track_number)),
+        SortKey::Year => some_first(a.year, b.year),
+        SortKey::Duration => some_first(a.duration_ms, b.duration_ms),
+    }
+}
+
+/// Builds the entries of a browse query from the known resources.
+pub fn browse(
+    resources: &HashMap<uuid::Uuid, Resource>,
+    query: &Browse,
+) -> Result<Vec<BrowseEntry>> {
+    let entries = match query {
+        Browse::Artists => artists(resources.values()),
+        Browse::Albums { artist } => albums(resources.values(), artist.as_deref()),
+        Browse::Genres => genres(resources.values()),
+        Browse::Tracks {
+            artist,
+            album,
+            genre,
+        } => tracks(
+            resources.values(),
+            artist.as_deref(),
+            album.as_deref(),
+            genre.as_deref(),
+        ),
+        Browse::Folder { provider, path } => folder(resources.values(), *provider, path),
+        Browse::Playlist { resource } => expand(resources, std::slice::from_ref(resource))?
+            .iter()
+            .filter_map(|uid| resources.get(uid))
+            .map(|res| BrowseEntry::Resource(Box::new(res.to_web_interface())))
+            .collect(),
+    };
+
+    Ok(entries)
+}
+
+/// Replaces playlists among `uids` by the resources they list, so they can
+/// be queued as a unit. Entries that are not part of the library are skipped.
+pub fn expand(
+    resources: &HashMap<uuid::Uuid, Resource>,
+    uids: &[uuid::Uuid],
+) -> Result<Vec<uuid::Uuid>> {
+    let mut by_location = HashMap::new();
+    let mut expanded = Vec::new();
+
+    for uid in uids {
+        let res = resources
+            .get(uid)
+            .ok_or_else(|| anyhow!("Could not find a resource identified by {}", uid))?;
+
+        if res.media_type != MediaType::Playlist {
+            expanded.push(*uid);
+            continue;
+        }
+
+        if by_location.is_empty() {
+            by_location = resources
+                .values()
+                .map(|res| (&res.location, res.uuid))
+                .collect();
+        }
+
+        let count = expanded.len();
+        expand_playlist(
+            resources,
+            &by_location,
+            res,
+            &mut vec![res.uuid],
+            &mut expanded,
+        );
+        if expanded.len() == count {
+            return Err(anyhow!(
+                "Playlist {} lists no resources of the library",
+                res.name
+            ));
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Appends the entries of `playlist` to `expanded`, descending into nested
+/// playlists unless they are already being expanded.
+fn expand_playlist(
+    resources: &HashMap<uuid::Uuid, Resource>,
+    by_location: &HashMap<&Location, uuid::Uuid>,
+    playlist: &Resource,
+    parents: &mut Vec<uuid::Uuid>,
+    expanded: &mut Vec<uuid::Uuid>,
+) {
+    for location in &playlist.entries {
+        let res = match by_location.get(location).and_then(|uid| resources.get(uid)) {
+            Some(res) => res,
+            None => {
+                log::debug!("Playlist {} lists unknown {:?}", playlist.name, location);
+                continue;
+            }
+        };
+
+        if res.media_type != MediaType::Playlist {
+            expanded.push(res.uuid);
+        } else if !parents.contains(&res.uuid) {
+            parents.push(res.uuid);
+            expand_playlist(resources, by_location, res, parents, expanded);
+            parents.pop();
+        }
+    }
+}
+
+/// Artist an album is filed under, preferring the album artist.
+fn album_artist(metadata: &Metadata) -> Option<&str> {
+    metadata
+        .album_artist
+        .as_deref()
+        .or(metadata.artist.as_deref())
+}
+
+fn matches(value: Option<&str>, filter: Option<&str>) -> bool {
+    filter.is_none_or(|filter| value == Some(filter))
+}
+
+fn artists<'a>(resources: impl Iterator<Item = &'a Resource>) -> Vec<BrowseEntry> {
+    let mut artists = BTreeMap::<&str, (BTreeSet<&str>, usize)>::new();
+
+    for res in resources {
+        if let Some(artist) = album_artist(&res.metadata) {
+            let (albums, tracks) = artists.entry(artist).or_default();
+            albums.extend(res.metadata.album.as_deref());
+            *tracks += 1;
+        }
+    }
+
+    artists
+        .into_iter()
+        .map(|(name, (albums, tracks))| BrowseEntry::Artist {
+            name: name.to_string(),
+            albums: albums.len(),
+            tracks,
+        })
+        .collect()
+}
+
+fn albums<'a>(
+    resources: impl Iterator<Item = &'a Resource>,
+    artist: Option<&str>,
+) -> Vec<BrowseEntry> {
+    let mut albums = BTreeMap::<(&str, Option<&str>), usize>::new();
+
+    for res in resources {
+        let album_artist = album_artist(&res.metadata);
+        if !matches(album_artist, artist) {
+            continue;
+        }
+
+        if let Some(album) = res.metadata.album.as_deref() {
+            *albums.entry((album, album_artist)).or_default() += 1;
+        }
+    }
+
+    albums
+        .into_iter()
+        .map(|((name, artist), tracks)| BrowseEntry::Album {
+            name: name.to_string(),
+            artist: artist.map(str::to_string),
+            tracks,
+        })
+        .collect()
+}
+
+fn genres<'a>(resources: impl Iterator<Item = &'a Resource>) -> Vec<BrowseEntry> {
+    let mut genres = BTreeMap::<&str, usize>::new();
+
+    for res in resources {
+        if let Some(genre) = res.metadata.genre.as_deref() {
+            *genres.entry(genre).or_default() += 1;
+        }
+    }
+
+    genres
+        .into_iter()
+        .map(|(name, tracks)| BrowseEntry::Genre {
+            name: name.to_string(),
+            tracks,
+        })
+        .collect()
+}
+
+fn tracks<'a>(
+    resources: impl Iterator<Item = &'a Resource>,
+    artist: Option<&str>,
+    album: Option<&str>,
+    genre: Option<&str>,
+) -> Vec<BrowseEntry> {
+    let mut tracks: Vec<&Resource> = resources
+        .filter(|res| {
+            let metadata = &res.metadata;
+            (matches(metadata.artist.as_deref(), artist)
+                || matches(metadata.album_artist.as_deref(), artist))
+                && matches(metadata.album.as_deref(), album)
+                && matches(metadata.genre.as_deref(), genre)
+        })
+        .collect();
+
+    tracks.sort_by(|a, b| {
+        (a.metadata.disc_number, a.metadata.track_number, &a.name).cmp(&(
+            b.metadata.disc_number,
+            b.metadata.track_number,
+            &b.name,
+        ))
+    });
+
+    tracks
+        .into_iter()
+        .map(|res| BrowseEntry::Resource(Box::new(res.to_web_interface())))
+        .collect()
+}
+
+fn folder<'a>(
+    resources: impl Iterator<Item = &'a Resource>,
+    provider: uuid::Uuid,
+    path: &str,
+) -> Vec<BrowseEntry> {
+    let root = Path::new(path);
+    let mut folders = BTreeMap::<String, BTreeSet<String>>::new();
+    let mut files = Vec::new();
+
+    for res in resources.filter(|res| res.provider == provider) {
+        let relative = match Path::new(&res.name).strip_prefix(root) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+
+        let mut components = relative.components().filter_map(|c| match c {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        });
+
+        match (components.next(), components.next()) {
+            (Some(folder), Some(child)) => {
+                folders
+                    .entry(folder.to_string())
+                    .or_default()
+                    .insert(child.to_string());
+            }
+            (Some(_), None) => files.push(res),
+            _ => {}
+        }
+    }
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    folders
+        .into_iter()
+        .map(|(name, children)| BrowseEntry::Folder {
+            path: root.join(&name).to_string_lossy().into_owned(),
+            name,
+            children: children.len(),
+        })
+        .chain(
+            files
+                .into_iter()
+                .map(|res| BrowseEntry::Resource(Box::new(res.to_web_interface()))),
+        )
+        .collect()
+}
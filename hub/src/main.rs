@@ -1,9 +1,25 @@
+mod archive;
+mod archive_provider;
 mod config;
+mod fetcher;
 mod filesystem_provider;
 mod hub;
+mod json_file;
+mod library;
+mod media_server;
+mod media_type;
+mod metadata;
+mod playback;
+mod playlist;
+mod podcast_provider;
+mod positions;
 mod provider;
+mod queue;
 mod resource;
+mod resource_index;
 mod resource_manager;
+mod stream_provider;
+mod user_playlists;
 mod ws_server;
 
 use crate::ws_server::WebSocketServer;
@@ -32,16 +48,19 @@ async fn main() -> anyhow::Result<()> {
 
     let web_ui_address = config.web_ui_address;
     let sink_management_address = config.sink_management_address;
+    let media_address = config.media_address;
 
     let hub = Arc::new(hub::Hub::new(config));
 
     let web_ui = WebSocketServer::<WebUiRequest, WebUiResponse>::new(hub.clone());
     let web_ui = web_ui.listen(web_ui_address);
 
-    let sink_management = WebSocketServer::<SinkRequest, SinkResponse>::new(hub);
+    let sink_management = WebSocketServer::<SinkRequest, SinkResponse>::new(hub.clone());
     let sink_management = sink_management.listen(sink_management_address);
 
-    tokio::try_join!(sink_management, web_ui)?;
+    let media = media_server::serve(media_address, hub);
+
+    tokio::try_join!(sink_management, web_ui, media)?;
 
     Ok(())
 }
@@ -0,0 +1,377 @@
+use crate::archive::{open_entry, EntryContent, MAX_INFLATED_SIZE};
+use crate::hub::Hub;
+use crate::resource::Location;
+use anyhow::Result;
+use hyper::body::Bytes;
+use hyper::header::{
+    HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE,
+};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::io::SeekFrom;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+/// Path under which resources are served, followed by the resource id.
+pub const MEDIA_PATH: &str = "/media/";
+
+/// Serves the resources known to the hub over HTTP, so sinks do not need
+/// access to the hub's filesystem.
+pub async fn serve(address: SocketAddr, hub: Arc<Hub>) -> Result<()> {
+    let inflated = Arc::new(Mutex::new(InflatedEntries::default()));
+    let make_service = make_service_fn(move |_| {
+        let hub = hub.clone();
+        let inflated = inflated.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle(hub.clone(), inflated.clone(), req)
+            }))
+        }
+    });
+
+    log::info!("Serving media on {}", address);
+    Server::try_bind(&address)?.serve(make_service).await?;
+
+    Ok(())
+}
+
+async fn handle(
+    hub: Arc<Hub>,
+    inflated: Arc<Mutex<InflatedEntries>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    log::debug!("Media request {} {}", req.method(), req.uri());
+
+    let response = match serve_resource(&hub, &inflated, &req).await {
+        Ok(response) => response,
+        Err(status) => status_response(status),
+    };
+
+    Ok(response)
+}
+
+async fn serve_resource(
+    hub: &Hub,
+    inflated: &Mutex<InflatedEntries>,
+    req: &Request<Body>,
+) -> Result<Response<Body>, StatusCode> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return Err(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    let uid = req
+        .uri()
+        .path()
+        .strip_prefix(MEDIA_PATH)
+        .and_then(|uid| uuid::Uuid::parse_str(uid).ok())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let (content, path) = match hub.resource_location(&uid).await {
+        Some(Location::File(path)) => {
+            let (file, size) = open_file(&path).await?;
+            let content = Content::File {
+                file,
+                offset: 0,
+                size,
+            };
+            (content, path)
+        }
+        Some(Location::Archive { archive, entry }) => (
+            open_archive_entry(&archive, &entry, inflated).await?,
+            PathBuf::from(entry),
+        ),
+        Some(Location::Url(_)) | None => return Err(StatusCode::NOT_FOUND),
+    };
+    let size = content.size();
+
+    let range = match req
+        .headers()
+        .get(RANGE)
+        .map_or(RangeRequest::Whole, |range| parse_range(range, size))
+    {
+        RangeRequest::Part(range) => Some(range),
+        RangeRequest::Unsatisfiable => {
+            let mut response = status_response(StatusCode::RANGE_NOT_SATISFIABLE);
+            response.headers_mut().insert(
+                CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{}", size)).unwrap(),
+            );
+            return Ok(response);
+        }
+        RangeRequest::Whole => None,
+    };
+
+    let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    let mut response = Response::builder()
+        .header(CONTENT_TYPE, mime.as_ref())
+        .header(ACCEPT_RANGES, "bytes");
+
+    let (start, length) = match &range {
+        Some(range) => {
+            response = response.status(StatusCode::PARTIAL_CONTENT).header(
+                CONTENT_RANGE,
+                format!("bytes {}-{}/{}", range.start(), range.end(), size),
+            );
+            (*range.start(), range.end() - range.start() + 1)
+        }
+        None => (0, size),
+    };
+    response = response.header(CONTENT_LENGTH, length);
+
+    let body = if req.method() == Method::HEAD {
+        Body::empty()
+    } else {
+        match content {
+            Content::File {
+                mut file, offset, ..
+            } => {
+                file.seek(SeekFrom::Start(offset + start))
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                Body::wrap_stream(ReaderStream::new(file.take(length)))
+            }
+            Content::Bytes(bytes) => {
+                let start = start as usize;
+                Body::from(bytes.slice(start..start + length as usize))
+            }
+        }
+    };
+
+    response
+        .body(body)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Content of a resource, either a region of a file or data held in memory.
+enum Content {
+    File {
+        file: tokio::fs::File,
+        offset: u64,
+        size: u64,
+    },
+    Bytes(Bytes),
+}
+
+impl Content {
+    fn size(&self) -> u64 {
+        match self {
+            Content::File { size, .. } => *size,
+            Content::Bytes(bytes) => bytes.len() as u64,
+        }
+    }
+}
+
+async fn open_file(path: &Path) -> Result<(tokio::fs::File, u64), StatusCode> {
+    let file = tokio::fs::File::open(path).await.map_err(|e| {
+        log::warn!("Cannot open {}: {}", path.display(), e);
+        StatusCode::NOT_FOUND
+    })?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .len();
+
+    Ok((file, size))
+}
+
+/// Opens an archive entry without extracting it, uncompressed entries are
+/// streamed from their region of the archive file. Compressed entries are
+/// kept in memory for the range requests that follow while seeking.
+async fn open_archive_entry(
+    archive: &Path,
+    entry: &str,
+    inflated: &Mutex<InflatedEntries>,
+) -> Result<Content, StatusCode> {
+    let modified = tokio::fs::metadata(archive)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?
+        .modified()
+        .ok();
+    if let Some(modified) = modified {
+        if let Some(bytes) = inflated.lock().unwrap().get(archive, entry, modified) {
+            return Ok(Content::Bytes(bytes));
+        }
+    }
+
+    let location = (archive.to_path_buf(), entry.to_string());
+    let content = tokio::task::spawn_blocking(move || open_entry(&location.0, &location.1))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            log::warn!("Cannot open {} in {}: {}", entry, archive.display(), e);
+            StatusCode::NOT_FOUND
+        })?;
+
+    match content {
+        EntryContent::Slice { offset, size } => {
+            let (file, _) = open_file(archive).await?;
+            Ok(Content::File { file, offset, size })
+        }
+        EntryContent::Bytes(bytes) => {
+            let bytes = Bytes::from(bytes);
+            if let Some(modified) = modified {
+                inflated
+                    .lock()
+                    .unwrap()
+                    .insert(archive, entry, modified, bytes.clone());
+            }
+            Ok(Content::Bytes(bytes))
+        }
+    }
+}
+
+/// Recently decompressed archive entries, least recently used first. Their
+/// total size is bounded by [`MAX_INFLATED_SIZE`].
+#[derive(Default)]
+struct InflatedEntries {
+    entries: VecDeque<InflatedEntry>,
+}
+
+struct InflatedEntry {
+    archive: PathBuf,
+    entry: String,
+    modified: SystemTime,
+    content: Bytes,
+}
+
+impl InflatedEntries {
+    /// Returns the content of the entry unless the archive was modified
+    /// since it was decompressed.
+    fn get(&mut self, archive: &Path, entry: &str, modified: SystemTime) -> Option<Bytes> {
+        let index = self
+            .entries
+            .iter()
+            .position(|cached| cached.archive == archive && cached.entry == entry)?;
+        let cached = self.entries.remove(index)?;
+        if cached.modified != modified {
+            return None;
+        }
+
+        let content = cached.content.clone();
+        self.entries.push_back(cached);
+        Some(content)
+    }
+
+    fn insert(&mut self, archive: &Path, entry: &str, modified: SystemTime, content: Bytes) {
+        let mut size: usize = self.entries.iter().map(|cached| cached.content.len()).sum();
+        while size + content.len() > MAX_INFLATED_SIZE as usize {
+            match self.entries.pop_front() {
+                Some(evicted) => size -= evicted.content.len(),
+                None => break,
+            }
+        }
+
+        self.entries.push_back(InflatedEntry {
+            archive: archive.to_path_buf(),
+            entry: entry.to_string(),
+            modified,
+            content,
+        });
+    }
+}
+
+/// How to answer a request with a `Range` header.
+#[derive(Debug, PartialEq, Eq)]
+enum RangeRequest {
+    Whole,
+    Part(RangeInclusive<u64>),
+    Unsatisfiable,
+}
+
+/// Parses a single `bytes` range. Multiple ranges are not supported and,
+/// like malformed ones, answered with the whole content as RFC 9110 allows.
+fn parse_range(header: &HeaderValue, size: u64) -> RangeRequest {
+    let range = match header.to_str().ok().and_then(|h| h.strip_prefix("bytes=")) {
+        Some(range) if !range.contains(',') => range.trim(),
+        _ => return RangeRequest::Whole,
+    };
+    let (start, end) = match range.split_once('-') {
+        Some(bounds) => bounds,
+        None => return RangeRequest::Whole,
+    };
+
+    let (start, end) = if start.is_empty() {
+        match end.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) => (size.saturating_sub(suffix), None),
+            Err(_) => return RangeRequest::Whole,
+        }
+    } else {
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return RangeRequest::Whole,
+        };
+        match end {
+            "" => (start, None),
+            end => match end.parse::<u64>() {
+                Ok(end) if end >= start => (start, Some(end)),
+                _ => return RangeRequest::Whole,
+            },
+        }
+    };
+
+    if start >= size {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    let last = size - 1;
+    RangeRequest::Part(start..=end.map_or(last, |end| end.min(last)))
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(header: &str, size: u64) -> RangeRequest {
+        parse_range(&HeaderValue::from_str(header).unwrap(), size)
+    }
+
+    #[test]
+    fn parses_bounded_and_open_ended_ranges() {
+        assert_eq!(range("bytes=0-99", 1000), RangeRequest::Part(0..=99));
+        assert_eq!(range("bytes=500-", 1000), RangeRequest::Part(500..=999));
+        assert_eq!(range("bytes=999-", 1000), RangeRequest::Part(999..=999));
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(range("bytes=-100", 1000), RangeRequest::Part(900..=999));
+        assert_eq!(range("bytes=-5000", 1000), RangeRequest::Part(0..=999));
+        assert_eq!(range("bytes=-0", 1000), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn limits_ranges_past_the_end() {
+        assert_eq!(range("bytes=900-5000", 1000), RangeRequest::Part(900..=999));
+        assert_eq!(range("bytes=1000-", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(range("bytes=1000-1100", 1000), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn cannot_satisfy_ranges_of_empty_content() {
+        assert_eq!(range("bytes=0-", 0), RangeRequest::Unsatisfiable);
+        assert_eq!(range("bytes=0-10", 0), RangeRequest::Unsatisfiable);
+        assert_eq!(range("bytes=-10", 0), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn ignores_multiple_and_malformed_ranges() {
+        assert_eq!(range("bytes=0-9,20-29", 1000), RangeRequest::Whole);
+        assert_eq!(range("bytes=9-0", 1000), RangeRequest::Whole);
+        assert_eq!(range("bytes=x-", 1000), RangeRequest::Whole);
+        assert_eq!(range("items=0-9", 1000), RangeRequest::Whole);
+    }
+}
@@ -0,0 +1,96 @@
+use messages::web_interface::MediaType;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "aac", "aiff", "alac", "ape", "flac", "m4a", "mka", "mp2", "mp3", "mpc", "oga", "ogg", "opus",
+    "wav", "wma", "wv",
+];
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "avi", "flv", "m2ts", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg", "ogv", "ts", "webm", "wmv",
+];
+const IMAGE_EXTENSIONS: &[&str] = &["bmp", "gif", "jpeg", "jpg", "png", "tif", "tiff", "webp"];
+const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls", "xspf"];
+
+/// Checks whether the file at `path` has one of the playlist extensions.
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| PLAYLIST_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Caps name GStreamer's type finder reports for the container of the file
+/// at `path`, used to check it against the containers a sink supports.
+pub fn container_caps(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+
+    let caps = match extension.as_str() {
+        "aac" => "audio/x-adts",
+        "aiff" => "audio/x-aiff",
+        "ape" => "application/x-ape",
+        "flac" => "audio/x-flac",
+        "m4a" | "m4v" | "mov" | "mp4" => "video/quicktime",
+        "mka" | "mkv" => "video/x-matroska",
+        "mp2" | "mp3" => "audio/mpeg",
+        "oga" | "ogg" | "ogv" | "opus" => "application/ogg",
+        "wav" => "audio/x-wav",
+        "webm" => "video/webm",
+        "wma" | "wmv" => "video/x-ms-asf",
+        "avi" => "video/x-msvideo",
+        "wv" => "audio/x-wavpack",
+        _ => return None,
+    };
+
+    Some(caps)
+}
+
+/// Classifies the file at `path` by its extension, falling back to the
+/// magic bytes at the start of the file for unknown extensions.
+pub fn classify(path: &Path) -> MediaType {
+    classify_extension(path)
+        .or_else(|| sniff(path))
+        .unwrap_or(MediaType::Unknown)
+}
+
+/// Classifies `path` by its extension alone, e.g. for archive entries.
+pub fn classify_extension(path: &Path) -> Option<MediaType> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+
+    match extension.as_str() {
+        e if AUDIO_EXTENSIONS.contains(&e) => Some(MediaType::Audio),
+        e if VIDEO_EXTENSIONS.contains(&e) => Some(MediaType::Video),
+        e if IMAGE_EXTENSIONS.contains(&e) => Some(MediaType::Image),
+        e if PLAYLIST_EXTENSIONS.contains(&e) => Some(MediaType::Playlist),
+        _ => None,
+    }
+}
+
+fn sniff(path: &Path) -> Option<MediaType> {
+    let mut header = [0u8; 12];
+    let length = File::open(path).ok()?.read(&mut header).ok()?;
+    let header = &header[..length];
+
+    let media_type = if header.starts_with(b"ID3")
+        || header.starts_with(b"fLaC")
+        || header.starts_with(b"OggS")
+        || (header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WAVE"))
+    {
+        MediaType::Audio
+    } else if header.starts_with(&[0x1a, 0x45, 0xdf, 0xa3])
+        || (header.starts_with(b"RIFF") && header.get(8..12) == Some(b"AVI "))
+    {
+        MediaType::Video
+    } else if header.starts_with(&[0x89, b'P', b'N', b'G'])
+        || header.starts_with(&[0xff, 0xd8, 0xff])
+        || header.starts_with(b"GIF8")
+    {
+        MediaType::Image
+    } else if header.starts_with(b"#EXTM3U") || header.starts_with(b"[playlist]") {
+        MediaType::Playlist
+    } else {
+        return None;
+    };
+
+    Some(media_type)
+}
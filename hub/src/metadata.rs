@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use messages::web_interface::Metadata;
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::{MetadataOptions, StandardTagKey, Tag};
+use symphonia::core::probe::Hint;
+
+/// Reads the tags (ID3, Vorbis comments, MP4 atoms, ...) and the duration of
+/// the media file at `path`.
+pub fn read_metadata(path: &Path) -> Result<Metadata> {
+    let file = File::open(path)?;
+    read_metadata_from(Box::new(file), path)
+        .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))
+}
+
+/// Reads the tags and the duration from `source`, `name` is used to guess
+/// the format by its extension.
+pub fn read_metadata_from(source: Box<dyn MediaSource>, name: &Path) -> Result<Metadata> {
+    let stream = MediaSourceStream::new(source, Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = name.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    let mut metadata = Metadata::default();
+
+    // Tags found by the container reader take precedence over the ones
+    // preceding it, e.g. an ID3v2 header in front of a FLAC stream.
+    if let Some(revision) = probed.format.metadata().current() {
+        apply_tags(&mut metadata, revision.tags());
+    }
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        apply_tags(&mut metadata, revision.tags());
+    }
+
+    if let Some(track) = probed.format.default_track() {
+        let params = &track.codec_params;
+        if let (Some(time_base), Some(frames)) = (params.time_base, params.n_frames) {
+            let time = time_base.calc_time(frames);
+            metadata.duration_ms = Some(time.seconds * 1000 + (time.frac * 1000.0) as u64);
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Fills the fields of `metadata` that are still unknown from `tags`.
+fn apply_tags(metadata: &mut Metadata, tags: &[Tag]) {
+    for tag in tags {
+        let value = tag.value.to_string();
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        let key = match tag.std_key {
+            Some(key) => key,
+            None => continue,
+        };
+
+        match key {
+            StandardTagKey::TrackTitle => set(&mut metadata.title, value.to_string()),
+            StandardTagKey::Artist => set(&mut metadata.artist, value.to_string()),
+            StandardTagKey::Album => set(&mut metadata.album, value.to_string()),
+            StandardTagKey::AlbumArtist => set(&mut metadata.album_artist, value.to_string()),
+            StandardTagKey::Genre => set(&mut metadata.genre, value.to_string()),
+            StandardTagKey::TrackNumber => set_opt(&mut metadata.track_number, parse_number(value)),
+            StandardTagKey::DiscNumber => set_opt(&mut metadata.disc_number, parse_number(value)),
+            StandardTagKey::Date | StandardTagKey::OriginalDate => {
+                set_opt(&mut metadata.year, parse_number(value))
+            }
+            _ => {}
+        }
+    }
+}
+
+fn set<T>(field: &mut Option<T>, value: T) {
+    if field.is_none() {
+        *field = Some(value);
+    }
+}
+
+fn set_opt<T>(field: &mut Option<T>, value: Option<T>) {
+    if let Some(value) = value {
+        set(field, value);
+    }
+}
+
+/// Parses the leading number of values like `3/12` or `2004-05-01`.
+fn parse_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
@@ -0,0 +1,96 @@
+use messages::sink_management::{PlaybackSnapshot, PlaybackState};
+use messages::web_interface;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Playback {
+    pub resource: Option<uuid::Uuid>,
+    pub state: PlaybackState,
+    pub position_ms: u64,
+    pub duration_ms: Option<u64>,
+    pub buffering: Option<u32>,
+    pub error: Option<String>,
+    pub stream_title: Option<String>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            resource: None,
+            state: PlaybackState::Stopped,
+            position_ms: 0,
+            duration_ms: None,
+            buffering: None,
+            error: None,
+            stream_title: None,
+        }
+    }
+}
+
+impl Playback {
+    pub fn from_snapshot(snapshot: PlaybackSnapshot) -> Self {
+        Self {
+            resource: Some(snapshot.resource),
+            state: snapshot.state,
+            position_ms: snapshot.position_ms,
+            duration_ms: snapshot.duration_ms,
+            ..Self::default()
+        }
+    }
+
+    pub fn start(&mut self, resource: uuid::Uuid) {
+        *self = Self {
+            resource: Some(resource),
+            ..Self::default()
+        };
+    }
+
+    pub fn set_state(&mut self, state: PlaybackState) {
+        self.state = state;
+        if state != PlaybackState::Buffering {
+            self.buffering = None;
+        }
+        if state == PlaybackState::Playing {
+            self.error = None;
+        }
+    }
+
+    pub fn set_position(&mut self, position_ms: u64, duration_ms: Option<u64>) {
+        self.position_ms = position_ms;
+        self.duration_ms = duration_ms;
+    }
+
+    pub fn set_buffering(&mut self, percent: u32) {
+        self.buffering = if percent < 100 { Some(percent) } else { None };
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn set_stream_title(&mut self, title: String) {
+        self.stream_title = Some(title);
+    }
+
+    pub fn to_web_interface(&self) -> web_interface::Playback {
+        web_interface::Playback {
+            resource: self.resource,
+            state: self.state,
+            position_ms: self.position_ms,
+            duration_ms: self.duration_ms,
+            buffering: self.buffering,
+            error: self.error.clone(),
+            stream_title: self.stream_title.clone(),
+        }
+    }
+
+    /// The sink lost its connection, so nothing is known to be playing anymore.
+    pub fn disconnect(&mut self) {
+        self.state = PlaybackState::Stopped;
+        self.buffering = None;
+    }
+
+    pub fn finish(&mut self) {
+        self.state = PlaybackState::Stopped;
+        self.position_ms = self.duration_ms.unwrap_or(self.position_ms);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::resource::Location;
+use anyhow::{bail, Result};
+use percent_encoding::percent_decode_str;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    /// Path or URL exactly as written in the playlist.
+    pub location: String,
+    pub title: Option<String>,
+}
+
+/// Reads the entries of an M3U, PLS or XSPF playlist, chosen by extension.
+pub fn read_playlist(path: &Path) -> Result<Vec<PlaylistEntry>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    // Plain M3U and PLS files predate UTF-8 and are often Latin-1 encoded.
+    let legacy = matches!(extension.as_deref(), Some("m3u") | Some("pls"));
+    let content = decode(std::fs::read(path)?, legacy);
+
+    match extension.as_deref() {
+        Some("m3u") | Some("m3u8") => Ok(parse_m3u(&content)),
+        Some("pls") => Ok(parse_pls(&content)),
+        Some("xspf") => parse_xspf(&content),
+        _ => bail!("Unsupported playlist format of {}", path.display()),
+    }
+}
+
+/// Decodes UTF-8, falling back to Latin-1 if `legacy` is set. Invalid
+/// sequences are replaced otherwise.
+fn decode(bytes: Vec<u8>, legacy: bool) -> String {
+    match String::from_utf8(bytes) {
+        Ok(content) => content
+            .strip_prefix('\u{feff}')
+            .map(str::to_string)
+            .unwrap_or(content),
+        Err(e) if legacy => e.as_bytes().iter().map(|&byte| byte as char).collect(),
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    }
+}
+
+fn parse_m3u(content: &str) -> Vec<PlaylistEntry> {
+    let mut entries = Vec::new();
+    let mut title = None;
+
+    for line in content.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            title = info
+                .split_once(',')
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push(PlaylistEntry {
+                location: line.to_string(),
+                title: title.take(),
+            });
+        }
+    }
+
+    entries
+}
+
+fn parse_pls(content: &str) -> Vec<PlaylistEntry> {
+    let mut files = BTreeMap::<u32, String>::new();
+    let mut titles = BTreeMap::<u32, String>::new();
+
+    for line in content.lines().map(str::trim) {
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim().to_string()),
+            None => continue,
+        };
+
+        if let Some(index) = key.strip_prefix("file").and_then(|i| i.parse().ok()) {
+            files.insert(index, value);
+        } else if let Some(index) = key.strip_prefix("title").and_then(|i| i.parse().ok()) {
+            titles.insert(index, value);
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|(index, location)| PlaylistEntry {
+            location,
+            title: titles.remove(&index),
+        })
+        .collect()
+}
+
+/// Reads the tracks of an XSPF playlist. Relative locations are URI
+/// references, they are decoded to plain paths like the other formats use.
+fn parse_xspf(content: &str) -> Result<Vec<PlaylistEntry>> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut elements = Vec::<String>::new();
+    let mut location = None;
+    let mut title = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                if name == "track" {
+                    location = None;
+                    title = None;
+                }
+                elements.push(name);
+            }
+            Event::End(_) if elements.last().map(String::as_str) == Some("track") => {
+                elements.pop();
+                entries.extend(location.take().map(|location| PlaylistEntry {
+                    location,
+                    title: title.take(),
+                }));
+            }
+            Event::End(_) => {
+                elements.pop();
+            }
+            Event::Text(text) => {
+                let in_track = elements.len() >= 2 && elements[elements.len() - 2] == "track";
+                let text = text.unescape()?.trim().to_string();
+                match elements.last().map(String::as_str) {
+                    Some("location") if in_track && location.is_none() => {
+                        location = Some(decode_uri(&text))
+                    }
+                    Some("title") if in_track && !text.is_empty() => title = Some(text),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Percent-decodes relative and `file` URIs, other URLs are kept as they are.
+fn decode_uri(uri: &str) -> String {
+    if is_url(uri) && !uri.starts_with("file://") {
+        return uri.to_string();
+    }
+
+    percent_decode_str(uri).decode_utf8_lossy().into_owned()
+}
+
+fn is_url(location: &str) -> bool {
+    location
+        .split_once("://")
+        .is_some_and(|(scheme, _)| !scheme.is_empty() && scheme.chars().all(char::is_alphanumeric))
+}
+
+/// Resolves a playlist entry to the location it refers to. Relative paths
+/// are taken relative to the folder containing the playlist.
+pub fn resolve(playlist: &Path, entry: &str) -> Location {
+    let path = match entry.strip_prefix("file://") {
+        Some(path) => Path::new(path),
+        None if is_url(entry) => return Location::Url(entry.to_string()),
+        None => Path::new(entry),
+    };
+
+    let path = match playlist.parent() {
+        Some(folder) if path.is_relative() => folder.join(path),
+        _ => path.to_path_buf(),
+    };
+
+    Location::File(normalize(&path))
+}
+
+/// Removes `.` and `..` components without touching the filesystem, so the
+/// paths compare equal to the ones found while scanning.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(location: &str, title: Option<&str>) -> PlaylistEntry {
+        PlaylistEntry {
+            location: location.to_string(),
+            title: title.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_m3u() {
+        let content = "#EXTM3U\r\n\
+            #EXTINF:123,Artist - Title\r\n\
+            music/track.mp3\r\n\
+            \r\n\
+            # comment\r\n\
+            #EXTINF:-1,\r\n\
+            http://example.com/stream\r\n\
+            /abs/other.flac\r\n";
+
+        assert_eq!(
+            parse_m3u(content),
+            vec![
+                entry("music/track.mp3", Some("Artist - Title")),
+                entry("http://example.com/stream", None),
+                entry("/abs/other.flac", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_pls_in_index_order() {
+        let content = "[playlist]\n\
+            File2=b.mp3\n\
+            Title2=B\n\
+            file1 = a.mp3\n\
+            Length1=-1\n\
+            NumberOfEntries=2\n\
+            Version=2\n";
+
+        assert_eq!(
+            parse_pls(content),
+            vec![entry("a.mp3", None), entry("b.mp3", Some("B"))]
+        );
+    }
+
+    #[test]
+    fn parses_xspf() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <title>Mix</title>
+              <trackList>
+                <track>
+                  <location>Some%20Album/01%20Track.flac</location>
+                  <title>First &amp; Best</title>
+                </track>
+                <track><title>Without location</title></track>
+                <track>
+                  <location>http://example.com/a%20b.mp3</location>
+                  <location>http://example.com/fallback.mp3</location>
+                </track>
+              </trackList>
+            </playlist>"#;
+
+        assert_eq!(
+            parse_xspf(content).unwrap(),
+            vec![
+                entry("Some Album/01 Track.flac", Some("First & Best")),
+                entry("http://example.com/a%20b.mp3", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_legacy_playlists_as_latin1() {
+        let content = b"Caf\xe9/Tr\xe4ck.mp3".to_vec();
+        assert_eq!(decode(content.clone(), true), "Café/Träck.mp3");
+        assert_eq!(decode(content, false), "Caf\u{fffd}/Tr\u{fffd}ck.mp3");
+        assert_eq!(
+            decode("\u{feff}Café.mp3".as_bytes().to_vec(), true),
+            "Café.mp3"
+        );
+    }
+
+    #[test]
+    fn resolves_entries_relative_to_the_playlist() {
+        let playlist = Path::new("/music/lists/mix.m3u");
+
+        assert_eq!(
+            resolve(playlist, "../album/./01.mp3"),
+            Location::File(PathBuf::from("/music/album/01.mp3"))
+        );
+        assert_eq!(
+            resolve(playlist, "/other/02.mp3"),
+            Location::File(PathBuf::from("/other/02.mp3"))
+        );
+        assert_eq!(
+            resolve(playlist, "file:///other/../03.mp3"),
+            Location::File(PathBuf::from("/03.mp3"))
+        );
+        assert_eq!(
+            resolve(playlist, "https://example.com/stream"),
+            Location::Url("https://example.com/stream".to_string())
+        );
+    }
+}
@@ -0,0 +1,638 @@
+use crate::fetcher::Fetcher;
+use crate::media_type::classify;
+use crate::provider::{reconcile, Provider, Published, ResourceProviderInterface, ScanProgress};
+use crate::resource::{provider_id, resource_id, Location, Resource};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, FixedOffset};
+use messages::web_interface::{MediaType, Metadata, ProviderKind, ScanStatus};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::{unbounded_channel as channel, UnboundedSender as Sender};
+use tokio::sync::watch;
+
+/// Episodes of RSS and Atom podcast feeds. Episodes are streamed from their
+/// enclosure URLs, or served from disk once downloaded if a download path is
+/// configured.
+pub struct PodcastProvider {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub feeds: Vec<String>,
+    commands: Sender<Command>,
+    status: watch::Receiver<ScanStatus>,
+}
+
+enum Command {
+    Rescan(Sender<ScanProgress>),
+}
+
+impl PodcastProvider {
+    pub fn new(
+        name: String,
+        feeds: Vec<String>,
+        download_path: Option<PathBuf>,
+        fetcher: Arc<dyn Fetcher>,
+        known: Vec<Resource>,
+        resource_sender: Sender<ResourceProviderInterface>,
+    ) -> Self {
+        let (commands, mut command_receiver) = channel::<Command>();
+        let (status_sender, status) = watch::channel(ScanStatus::Pending);
+
+        let mut podcasts = Podcasts {
+            provider: name.clone(),
+            provider_id: provider_id(&name),
+            feeds: feeds.clone(),
+            download_path,
+            fetcher,
+            resource_sender,
+            known: known.iter().map(|res| (res.uuid, res.clone())).collect(),
+        };
+
+        tokio::spawn(async move {
+            // Episodes known from a previous run are available right away,
+            // also if the feeds cannot be fetched.
+            if !known.is_empty()
+                && podcasts
+                    .resource_sender
+                    .send(ResourceProviderInterface::Add(known))
+                    .is_err()
+            {
+                return;
+            }
+
+            let mut progress = None;
+            loop {
+                status_sender.send_replace(ScanStatus::Scanning);
+                let downloads = podcasts.refresh(progress).await;
+                status_sender.send_replace(ScanStatus::Idle);
+
+                match downloads {
+                    Ok(downloads) => podcasts.download(downloads).await,
+                    Err(e) => {
+                        log::error!("Podcast provider {} stopped: {}", podcasts.provider, e);
+                        return;
+                    }
+                }
+
+                progress = match command_receiver.recv().await {
+                    Some(Command::Rescan(progress)) => Some(progress),
+                    None => return,
+                };
+            }
+        });
+
+        Self {
+            id: provider_id(&name),
+            name,
+            feeds,
+            commands,
+            status,
+        }
+    }
+}
+
+/// State of the provider task.
+struct Podcasts {
+    provider: String,
+    provider_id: uuid::Uuid,
+    feeds: Vec<String>,
+    download_path: Option<PathBuf>,
+    fetcher: Arc<dyn Fetcher>,
+    resource_sender: Sender<ResourceProviderInterface>,
+    known: Published,
+}
+
+impl Podcasts {
+    /// Fetches the feeds and publishes the changes of their episodes.
+    /// Returns the episodes still to be downloaded, fails only if the hub is
+    /// gone.
+    async fn refresh(
+        &mut self,
+        progress: Option<Sender<ScanProgress>>,
+    ) -> Result<Vec<(Resource, PathBuf)>> {
+        let mut report = ScanProgress {
+            provider: self.provider_id,
+            ..Default::default()
+        };
+        let mut episodes = Vec::new();
+        let mut downloads = Vec::new();
+
+        for feed in &self.feeds {
+            report.files_visited += 1;
+            match self.fetch_feed(feed).await {
+                Ok(feed_episodes) => {
+                    for (episode, download) in feed_episodes {
+                        if let Some(file) = download {
+                            downloads.push((episode.clone(), file));
+                        }
+                        episodes.push(episode);
+                    }
+                }
+                Err(e) => {
+                    log::error!("Cannot read podcast feed {}: {}", feed, e);
+                    report.errors.push(format!("{}: {}", feed, e));
+                }
+            }
+        }
+
+        let messages = if report.errors.is_empty() {
+            reconcile(&mut self.known, episodes.clone())
+        } else {
+            // Episodes of feeds that could not be fetched are kept.
+            let mut known = self.known.clone();
+            let messages = reconcile(&mut known, episodes.clone())
+                .into_iter()
+                .filter(|msg| !matches!(msg, ResourceProviderInterface::Remove(_)))
+                .collect();
+            self.known.extend(known);
+            messages
+        };
+
+        for msg in messages {
+            self.resource_sender
+                .send(msg)
+                .map_err(|_| anyhow!("Resource receiver is gone"))?;
+        }
+
+        report.resources_found = episodes.len() as u64;
+        report.finished = true;
+        if let Some(progress) = progress {
+            progress.send(report).ok();
+        }
+
+        Ok(downloads)
+    }
+
+    async fn fetch_feed(&self, feed: &str) -> Result<Vec<(Resource, Option<PathBuf>)>> {
+        let content = self.fetcher.fetch(feed).await?;
+        let feed = parse_feed(&content)?;
+
+        Ok(feed
+            .episodes
+            .iter()
+            .map(|episode| self.resource(&feed.title, episode))
+            .collect())
+    }
+
+    /// Creates the resource of an episode, along with the file it still has
+    /// to be downloaded to.
+    fn resource(&self, feed_title: &str, episode: &Episode) -> (Resource, Option<PathBuf>) {
+        let uuid = resource_id(&self.provider, &episode.id);
+        let (location, download) = match self.download_file(uuid, episode) {
+            Some(path) if path.is_file() => (Location::File(path), None),
+            path => (Location::Url(episode.url.clone()), path),
+        };
+
+        let media_type = match episode.mime.as_deref() {
+            Some(mime) if mime.starts_with("audio/") => MediaType::Audio,
+            Some(mime) if mime.starts_with("video/") => MediaType::Video,
+            _ => classify(Path::new(url_path(&episode.url))),
+        };
+
+        let title = episode.title.clone().unwrap_or_else(|| episode.id.clone());
+
+        let resource = Resource {
+            uuid,
+            provider: self.provider_id,
+            name: format!(
+                "{}/{}",
+                feed_title.replace('/', "-"),
+                title.replace('/', "-")
+            ),
+            location,
+            media_type,
+            metadata: Metadata {
+                title: Some(title),
+                artist: episode.author.clone(),
+                album: Some(feed_title.to_string()),
+                year: episode.published.map(|date| date.year()),
+                duration_ms: episode.duration_ms,
+                published: episode.published.map(|date| date.to_rfc3339()),
+                description: episode.description.clone(),
+                ..Default::default()
+            },
+            entries: Vec::new(),
+            resumable: true,
+            size: None,
+            modified: None,
+        };
+
+        (resource, download)
+    }
+
+    /// File an episode is downloaded to, named after the resource id.
+    fn download_file(&self, uuid: uuid::Uuid, episode: &Episode) -> Option<PathBuf> {
+        let extension = Path::new(url_path(&episode.url))
+            .extension()
+            .and_then(|e| e.to_str())
+            .or_else(|| {
+                episode
+                    .mime
+                    .as_deref()
+                    .and_then(mime_guess::get_mime_extensions_str)
+                    .and_then(|extensions| extensions.first().copied())
+            })
+            .unwrap_or("bin");
+
+        self.download_path
+            .as_ref()
+            .map(|path| path.join(format!("{}.{}", uuid, extension)))
+    }
+
+    /// Downloads the episodes that are still streamed and switches them over
+    /// to the downloaded files.
+    async fn download(&mut self, downloads: Vec<(Resource, PathBuf)>) {
+        let download_path = match &self.download_path {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Err(e) = tokio::fs::create_dir_all(download_path).await {
+            log::error!("Cannot create {}: {}", download_path.display(), e);
+            return;
+        }
+
+        for (mut episode, file) in downloads {
+            let url = match &episode.location {
+                Location::Url(url) => url.clone(),
+                _ => continue,
+            };
+
+            log::info!("Downloading episode {} to {}", episode.name, file.display());
+            if let Err(e) = self.download_episode(&url, &file).await {
+                log::error!("Cannot download episode {}: {}", episode.name, e);
+                continue;
+            }
+
+            episode.location = Location::File(file);
+            self.known.insert(episode.uuid, episode.clone());
+            let msg = ResourceProviderInterface::Modify(vec![episode]);
+            if self.resource_sender.send(msg).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Streams the episode into a temporary file first, so interrupted
+    /// downloads are not taken for complete ones.
+    async fn download_episode(&self, url: &str, file: &Path) -> Result<()> {
+        let mut content = self.fetcher.open(url).await?;
+
+        let temporary = file.with_extension("part");
+        let mut download = tokio::fs::File::create(&temporary).await?;
+        let copied = async {
+            tokio::io::copy(&mut content, &mut download).await?;
+            download.sync_all().await
+        }
+        .await;
+        if let Err(e) = copied {
+            tokio::fs::remove_file(&temporary).await.ok();
+            return Err(e.into());
+        }
+        tokio::fs::rename(&temporary, file).await?;
+
+        Ok(())
+    }
+}
+
+struct Feed {
+    title: String,
+    episodes: Vec<Episode>,
+}
+
+struct Episode {
+    /// Guid of the item, the enclosure URL if there is none.
+    id: String,
+    title: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    published: Option<DateTime<FixedOffset>>,
+    url: String,
+    mime: Option<String>,
+    duration_ms: Option<u64>,
+}
+
+/// Parses an RSS feed, falling back to Atom. Items without an enclosure are
+/// skipped.
+fn parse_feed(content: &[u8]) -> Result<Feed> {
+    match rss::Channel::read_from(content) {
+        Ok(channel) => Ok(from_rss(&channel)),
+        Err(rss_error) => match atom_syndication::Feed::read_from(content) {
+            Ok(feed) => Ok(from_atom(&feed)),
+            Err(atom_error) => Err(anyhow!(
+                "Neither an RSS ({}) nor an Atom feed ({})",
+                rss_error,
+                atom_error
+            )),
+        },
+    }
+}
+
+fn from_rss(channel: &rss::Channel) -> Feed {
+    let episodes = channel
+        .items()
+        .iter()
+        .filter_map(|item| {
+            let enclosure = item.enclosure()?;
+            let itunes = item.itunes_ext();
+
+            Some(Episode {
+                id: item
+                    .guid()
+                    .map_or(enclosure.url(), |guid| guid.value())
+                    .to_string(),
+                title: item.title().map(str::to_string),
+                author: item
+                    .author()
+                    .or_else(|| itunes.and_then(|itunes| itunes.author()))
+                    .map(str::to_string),
+                description: item
+                    .description()
+                    .or_else(|| itunes.and_then(|itunes| itunes.summary()))
+                    .map(str::to_string),
+                published: item
+                    .pub_date()
+                    .and_then(|date| DateTime::parse_from_rfc2822(date).ok()),
+                url: enclosure.url().to_string(),
+                mime: Some(enclosure.mime_type().to_string()).filter(|mime| !mime.is_empty()),
+                duration_ms: itunes
+                    .and_then(|itunes| itunes.duration())
+                    .and_then(parse_duration),
+            })
+        })
+        .collect();
+
+    Feed {
+        title: channel.title().to_string(),
+        episodes,
+    }
+}
+
+fn from_atom(feed: &atom_syndication::Feed) -> Feed {
+    let episodes = feed
+        .entries()
+        .iter()
+        .filter_map(|entry| {
+            let link = entry
+                .links()
+                .iter()
+                .find(|link| link.rel() == "enclosure")?;
+
+            Some(Episode {
+                id: entry.id().to_string(),
+                title: Some(entry.title().value.clone()),
+                author: entry
+                    .authors()
+                    .first()
+                    .map(|person| person.name().to_string()),
+                description: entry
+                    .summary()
+                    .map(|summary| summary.value.clone())
+                    .or_else(|| entry.content().and_then(|c| c.value()).map(str::to_string)),
+                published: Some(*entry.published().unwrap_or(entry.updated())),
+                url: link.href().to_string(),
+                mime: link.mime_type().map(str::to_string),
+                duration_ms: None,
+            })
+        })
+        .collect();
+
+    Feed {
+        title: feed.title().value.clone(),
+        episodes,
+    }
+}
+
+/// Parses iTunes durations given as seconds, `MM:SS` or `HH:MM:SS`.
+fn parse_duration(duration: &str) -> Option<u64> {
+    let seconds = duration.trim().split(':').try_fold(0u64, |total, part| {
+        Some(total * 60 + part.parse::<f64>().ok()? as u64)
+    })?;
+
+    Some(seconds * 1000)
+}
+
+/// Path of a URL without query and fragment, used to guess the file type.
+fn url_path(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+#[async_trait]
+impl Provider for PodcastProvider {
+    fn get_id(&self) -> uuid::Uuid {
+        self.id
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_kind(&self) -> ProviderKind {
+        ProviderKind::Podcast
+    }
+
+    fn get_roots(&self) -> Vec<String> {
+        self.feeds.clone()
+    }
+
+    fn get_scan_status(&self) -> ScanStatus {
+        *self.status.borrow()
+    }
+
+    fn rescan(&self, progress: Sender<ScanProgress>) -> anyhow::Result<()> {
+        self.commands
+            .send(Command::Rescan(progress))
+            .map_err(|_| anyhow!("Provider {} is not running", self.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::Content;
+    use std::collections::HashMap;
+    use tokio::sync::mpsc::UnboundedReceiver as Receiver;
+
+    const FEED: &str = "http://example.com/feed.xml";
+    const ENCLOSURE: &str = "http://example.com/episodes/1.mp3?token=abc";
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Show</title>
+    <link>http://example.com</link>
+    <description>A show</description>
+    <item>
+      <title>Pilot/Intro</title>
+      <guid>episode-1</guid>
+      <pubDate>Mon, 02 Jan 2023 10:00:00 +0000</pubDate>
+      <itunes:author>Host</itunes:author>
+      <itunes:duration>01:02:03</itunes:duration>
+      <enclosure url="http://example.com/episodes/1.mp3?token=abc" length="5" type="audio/mpeg"/>
+    </item>
+    <item>
+      <title>Announcement</title>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Atom show</title>
+  <id>urn:show</id>
+  <updated>2023-01-02T10:00:00Z</updated>
+  <entry>
+    <title>First</title>
+    <id>urn:show:1</id>
+    <updated>2023-01-03T10:00:00Z</updated>
+    <summary>The first episode</summary>
+    <link rel="enclosure" href="http://example.com/1.ogg" type="audio/ogg"/>
+  </entry>
+</feed>"#;
+
+    /// Serves documents from memory instead of fetching them.
+    struct StaticFetcher(HashMap<String, Vec<u8>>);
+
+    #[async_trait]
+    impl Fetcher for StaticFetcher {
+        async fn open(&self, location: &str) -> Result<Content> {
+            let content = self
+                .0
+                .get(location)
+                .ok_or_else(|| anyhow!("{} not found", location))?;
+            Ok(Box::new(std::io::Cursor::new(content.clone())))
+        }
+    }
+
+    fn podcasts(
+        documents: &[(&str, &[u8])],
+        download_path: Option<PathBuf>,
+    ) -> (Podcasts, Receiver<ResourceProviderInterface>) {
+        let documents = documents
+            .iter()
+            .map(|(location, content)| (location.to_string(), content.to_vec()))
+            .collect();
+        let (resource_sender, resources) = channel();
+
+        let podcasts = Podcasts {
+            provider: "Podcasts".to_string(),
+            provider_id: provider_id("Podcasts"),
+            feeds: vec![FEED.to_string()],
+            download_path,
+            fetcher: Arc::new(StaticFetcher(documents)),
+            resource_sender,
+            known: Published::new(),
+        };
+
+        (podcasts, resources)
+    }
+
+    #[test]
+    fn parses_rss_feeds() {
+        let feed = parse_feed(RSS.as_bytes()).unwrap();
+
+        assert_eq!(feed.title, "Show");
+        assert_eq!(feed.episodes.len(), 1);
+        let episode = &feed.episodes[0];
+        assert_eq!(episode.id, "episode-1");
+        assert_eq!(episode.title.as_deref(), Some("Pilot/Intro"));
+        assert_eq!(episode.author.as_deref(), Some("Host"));
+        assert_eq!(episode.url, ENCLOSURE);
+        assert_eq!(episode.mime.as_deref(), Some("audio/mpeg"));
+        assert_eq!(episode.duration_ms, Some(3_723_000));
+        assert_eq!(episode.published.map(|date| date.year()), Some(2023));
+    }
+
+    #[test]
+    fn parses_atom_feeds() {
+        let feed = parse_feed(ATOM.as_bytes()).unwrap();
+
+        assert_eq!(feed.title, "Atom show");
+        assert_eq!(feed.episodes.len(), 1);
+        let episode = &feed.episodes[0];
+        assert_eq!(episode.id, "urn:show:1");
+        assert_eq!(episode.description.as_deref(), Some("The first episode"));
+        assert_eq!(episode.url, "http://example.com/1.ogg");
+        assert_eq!(episode.mime.as_deref(), Some("audio/ogg"));
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse_feed(b"<html></html>").is_err());
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Some(90_000));
+        assert_eq!(parse_duration("01:30"), Some(90_000));
+        assert_eq!(parse_duration("1:00:00"), Some(3_600_000));
+        assert_eq!(parse_duration("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn refresh_publishes_episodes() {
+        let (mut podcasts, mut resources) = podcasts(&[(FEED, RSS.as_bytes())], None);
+
+        let downloads = podcasts.refresh(None).await.unwrap();
+
+        assert!(downloads.is_empty());
+        let episodes = match resources.try_recv() {
+            Ok(ResourceProviderInterface::Add(episodes)) => episodes,
+            _ => panic!("Episodes were not added"),
+        };
+        assert_eq!(episodes.len(), 1);
+        let episode = &episodes[0];
+        assert_eq!(episode.name, "Show/Pilot-Intro");
+        assert_eq!(episode.location, Location::Url(ENCLOSURE.to_string()));
+        assert_eq!(episode.media_type, MediaType::Audio);
+        assert_eq!(episode.metadata.album.as_deref(), Some("Show"));
+        assert!(episode.resumable);
+    }
+
+    #[tokio::test]
+    async fn refresh_keeps_episodes_of_unavailable_feeds() {
+        let (mut podcasts, mut resources) = podcasts(&[], None);
+        let feed = parse_feed(RSS.as_bytes()).unwrap();
+        let (episode, _) = podcasts.resource(&feed.title, &feed.episodes[0]);
+        podcasts.known.insert(episode.uuid, episode.clone());
+        let (progress_sender, mut progress) = channel();
+
+        podcasts.refresh(Some(progress_sender)).await.unwrap();
+
+        assert!(resources.try_recv().is_err());
+        assert!(podcasts.known.contains_key(&episode.uuid));
+        let report = progress.try_recv().unwrap();
+        assert!(report.finished);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn downloads_episodes() {
+        let download_path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let (mut podcasts, mut resources) = podcasts(
+            &[(FEED, RSS.as_bytes()), (ENCLOSURE, b"audio")],
+            Some(download_path.clone()),
+        );
+
+        let downloads = podcasts.refresh(None).await.unwrap();
+        podcasts.download(downloads).await;
+
+        assert!(matches!(
+            resources.try_recv(),
+            Ok(ResourceProviderInterface::Add(_))
+        ));
+        let episode = match resources.try_recv() {
+            Ok(ResourceProviderInterface::Modify(mut episodes)) => episodes.remove(0),
+            _ => panic!("Episode was not switched to the download"),
+        };
+        let file = match &episode.location {
+            Location::File(file) => file.clone(),
+            location => panic!("Episode is still at {:?}", location),
+        };
+        assert_eq!(file.extension().and_then(|e| e.to_str()), Some("mp3"));
+        assert_eq!(std::fs::read(&file).unwrap(), b"audio");
+        assert!(!file.with_extension("part").exists());
+
+        std::fs::remove_dir_all(download_path).unwrap();
+    }
+}
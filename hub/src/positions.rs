@@ -0,0 +1,23 @@
+use crate::json_file;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Playback positions of resumable resources persisted between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PositionsFile {
+    positions: HashMap<uuid::Uuid, u64>,
+}
+
+pub fn load_positions<P: AsRef<Path>>(path: P) -> Result<HashMap<uuid::Uuid, u64>> {
+    let content: PositionsFile = json_file::load(path)?;
+    Ok(content.positions)
+}
+
+pub async fn save_positions<P: AsRef<Path>>(
+    path: P,
+    positions: HashMap<uuid::Uuid, u64>,
+) -> Result<()> {
+    json_file::save_atomic(path, &PositionsFile { positions }).await
+}
@@ -1,15 +1,75 @@
 use crate::resource::Resource;
+use crate::resource_index::ScannedDirectories;
 use async_trait::async_trait;
+use messages::web_interface::{ProviderKind, ScanStatus};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc::UnboundedSender as Sender;
 
 #[async_trait]
 pub trait Provider {
+    fn get_id(&self) -> uuid::Uuid;
     fn get_name(&self) -> &str;
+    fn get_kind(&self) -> ProviderKind;
+    fn get_roots(&self) -> Vec<String>;
+    fn get_scan_status(&self) -> ScanStatus;
+
+    /// Starts a rescan in the background. Progress is reported through
+    /// `progress` and the result is reconciled by the provider itself.
+    fn rescan(&self, progress: Sender<ScanProgress>) -> anyhow::Result<()>;
 }
 
 pub enum ResourceProviderInterface {
     Add(Vec<Resource>),
-    // Remove
-    // Modify
+    Remove(Vec<uuid::Uuid>),
+    Modify(Vec<Resource>),
+    /// Directories read by the latest scan of a filesystem provider,
+    /// persisted in the resource index.
+    Directories {
+        provider: uuid::Uuid,
+        directories: ScannedDirectories,
+    },
+}
+
+/// Resources published by a provider, by id.
+pub type Published = HashMap<uuid::Uuid, Resource>;
+
+/// Compares `resources` to the ones published before and returns the
+/// messages that bring the hub up to date, unchanged resources are left out.
+/// `known` is updated to the new resources.
+pub fn reconcile(
+    known: &mut Published,
+    resources: Vec<Resource>,
+) -> Vec<ResourceProviderInterface> {
+    let current: HashSet<uuid::Uuid> = resources.iter().map(|res| res.uuid).collect();
+    let removed: Vec<uuid::Uuid> = known
+        .keys()
+        .filter(|uid| !current.contains(uid))
+        .copied()
+        .collect();
+
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    for res in &resources {
+        match known.get(&res.uuid) {
+            None => added.push(res.clone()),
+            Some(published) if published != res => modified.push(res.clone()),
+            Some(_) => {}
+        }
+    }
+    *known = resources.into_iter().map(|res| (res.uuid, res)).collect();
+
+    let mut messages = Vec::new();
+    if !removed.is_empty() {
+        messages.push(ResourceProviderInterface::Remove(removed));
+    }
+    if !added.is_empty() {
+        messages.push(ResourceProviderInterface::Add(added));
+    }
+    if !modified.is_empty() {
+        messages.push(ResourceProviderInterface::Modify(modified));
+    }
+
+    messages
 }
 
 impl std::fmt::Debug for ResourceProviderInterface {
@@ -17,3 +77,61 @@ impl std::fmt::Debug for ResourceProviderInterface {
         f.write_str("ResourceProviderInterface")
     }
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanProgress {
+    pub provider: uuid::Uuid,
+    pub files_visited: u64,
+    pub resources_found: u64,
+    /// Errors encountered since the previous progress report.
+    pub errors: Vec<String>,
+    pub finished: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{resource_id, Location};
+
+    fn station(name: &str) -> Resource {
+        Resource {
+            uuid: resource_id("Radio", name),
+            provider: uuid::Uuid::nil(),
+            name: name.to_string(),
+            location: Location::Url(format!("http://example.com/{}", name)),
+            media_type: Default::default(),
+            metadata: Default::default(),
+            entries: Vec::new(),
+            resumable: false,
+            size: None,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn reconcile_reports_only_changes() {
+        let mut known = Published::new();
+        let messages = reconcile(&mut known, vec![station("a"), station("b")]);
+        assert!(matches!(
+            messages.as_slice(),
+            [ResourceProviderInterface::Add(added)] if added.len() == 2
+        ));
+
+        let mut renamed = station("b");
+        renamed.metadata.title = Some("B".to_string());
+        let messages = reconcile(&mut known, vec![renamed, station("c")]);
+        assert!(matches!(
+            messages.as_slice(),
+            [
+                ResourceProviderInterface::Remove(removed),
+                ResourceProviderInterface::Add(added),
+                ResourceProviderInterface::Modify(modified),
+            ] if removed == &[station("a").uuid]
+                && added[0].name == "c"
+                && modified[0].name == "b"
+        ));
+
+        let resources = known.values().cloned().collect();
+        assert!(reconcile(&mut known, resources).is_empty());
+    }
+}
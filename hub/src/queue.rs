@@ -0,0 +1,198 @@
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+
+#[derive(Debug, Default)]
+pub struct Queue {
+    items: Vec<uuid::Uuid>,
+    current: Option<usize>,
+}
+
+impl Queue {
+    pub fn items(&self) -> &[uuid::Uuid] {
+        &self.items
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn current_resource(&self) -> Option<uuid::Uuid> {
+        self.items.get(self.current?).copied()
+    }
+
+    pub fn enqueue(&mut self, resources: impl IntoIterator<Item = uuid::Uuid>) {
+        self.items.extend(resources);
+    }
+
+    /// Inserts the resources right after the current one and makes the
+    /// first of them current.
+    pub fn play_now(&mut self, resources: &[uuid::Uuid]) -> Option<uuid::Uuid> {
+        let index = self.current.map_or(0, |current| current + 1);
+        let first = *resources.first()?;
+        self.items.splice(index..index, resources.iter().copied());
+        self.current = Some(index);
+        Some(first)
+    }
+
+    pub fn next(&mut self) -> Option<uuid::Uuid> {
+        let index = self.current.map_or(0, |current| current + 1);
+        let resource = *self.items.get(index)?;
+        self.current = Some(index);
+        Some(resource)
+    }
+
+    pub fn previous(&mut self) -> Option<uuid::Uuid> {
+        let index = self.current?.checked_sub(1)?;
+        let resource = *self.items.get(index)?;
+        self.current = Some(index);
+        Some(resource)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.current = None;
+    }
+
+    /// Removes the item at `index`. Removing the current item leaves the
+    /// queue positioned so that `next` continues with the following one.
+    pub fn remove(&mut self, index: usize) -> Result<uuid::Uuid> {
+        if index >= self.items.len() {
+            bail!("Queue index {} is out of range", index);
+        }
+
+        Ok(self.remove_item(index))
+    }
+
+    /// Removes every item referring to one of `resources`, e.g. because they
+    /// were deleted from the library, so the indices of the remaining items
+    /// match the resources listed for the queue.
+    pub fn remove_resources(&mut self, resources: &HashSet<uuid::Uuid>) {
+        for index in (0..self.items.len()).rev() {
+            if resources.contains(&self.items[index]) {
+                self.remove_item(index);
+            }
+        }
+    }
+
+    fn remove_item(&mut self, index: usize) -> uuid::Uuid {
+        let resource = self.items.remove(index);
+        self.current = match self.current {
+            Some(current) if index <= current => current.checked_sub(1),
+            current => current,
+        };
+
+        resource
+    }
+
+    pub fn move_item(&mut self, from: usize, to: usize) -> Result<()> {
+        if from >= self.items.len() || to >= self.items.len() {
+            bail!(
+                "Cannot move queue item from {} to {}, queue has {} items",
+                from,
+                to,
+                self.items.len()
+            );
+        }
+
+        let resource = self.items.remove(from);
+        self.items.insert(to, resource);
+
+        self.current = self.current.map(|current| {
+            if current == from {
+                to
+            } else if from < current && current <= to {
+                current - 1
+            } else if to <= current && current < from {
+                current + 1
+            } else {
+                current
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Queue of `len` distinct resources with the item at `current` playing.
+    fn filled(len: usize, current: Option<usize>) -> (Queue, Vec<uuid::Uuid>) {
+        let items: Vec<uuid::Uuid> = (0..len).map(|_| uuid::Uuid::new_v4()).collect();
+        let queue = Queue {
+            items: items.clone(),
+            current,
+        };
+        (queue, items)
+    }
+
+    #[test]
+    fn moving_items_keeps_the_current_resource() {
+        let (mut queue, items) = filled(5, Some(2));
+
+        queue.move_item(0, 4).unwrap();
+        assert_eq!(queue.current(), Some(1));
+        assert_eq!(queue.current_resource(), Some(items[2]));
+
+        queue.move_item(4, 0).unwrap();
+        assert_eq!(queue.current(), Some(2));
+        assert_eq!(queue.current_resource(), Some(items[2]));
+
+        queue.move_item(2, 3).unwrap();
+        assert_eq!(queue.current(), Some(3));
+        assert_eq!(queue.current_resource(), Some(items[2]));
+
+        queue.move_item(1, 2).unwrap();
+        assert_eq!(queue.current(), Some(3));
+        assert_eq!(
+            queue.items(),
+            &[items[0], items[3], items[1], items[2], items[4]]
+        );
+
+        assert!(queue.move_item(0, 5).is_err());
+    }
+
+    #[test]
+    fn removing_the_current_item_continues_with_the_following_one() {
+        let (mut queue, items) = filled(3, Some(1));
+
+        assert_eq!(queue.remove(1).unwrap(), items[1]);
+        assert_eq!(queue.current(), Some(0));
+        assert_eq!(queue.next(), Some(items[2]));
+
+        let (mut queue, items) = filled(3, Some(0));
+        queue.remove(0).unwrap();
+        assert_eq!(queue.current(), None);
+        assert_eq!(queue.next(), Some(items[1]));
+    }
+
+    #[test]
+    fn removing_the_last_item() {
+        let (mut queue, items) = filled(3, Some(2));
+
+        queue.remove(2).unwrap();
+        assert_eq!(queue.current_resource(), Some(items[1]));
+        assert_eq!(queue.next(), None);
+
+        let (mut queue, items) = filled(3, Some(0));
+        queue.remove(2).unwrap();
+        assert_eq!(queue.current_resource(), Some(items[0]));
+        assert!(queue.remove(2).is_err());
+    }
+
+    #[test]
+    fn removing_resources_before_the_current_one() {
+        let (mut queue, items) = filled(6, Some(3));
+        let removed = HashSet::from([items[0], items[2], items[5]]);
+
+        queue.remove_resources(&removed);
+        assert_eq!(queue.items(), &[items[1], items[3], items[4]]);
+        assert_eq!(queue.current(), Some(1));
+        assert_eq!(queue.current_resource(), Some(items[3]));
+
+        queue.remove_resources(&HashSet::from([items[1], items[3]]));
+        assert_eq!(queue.items(), &[items[4]]);
+        assert_eq!(queue.next(), Some(items[4]));
+    }
+}
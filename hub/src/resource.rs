@@ -1,8 +1,98 @@
-use std::path::PathBuf;
+use messages::web_interface::{self, MediaType, Metadata};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-#[derive(Debug, Clone)]
+/// Where the content of a resource comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Location {
+    /// Local file served to the sinks by the media server.
+    File(PathBuf),
+    /// Remote stream the sinks open directly.
+    Url(String),
+    /// Entry of a zip or tar archive, served by the media server without
+    /// extracting it.
+    Archive { archive: PathBuf, entry: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Resource {
     pub uuid: uuid::Uuid,
+    /// Id of the provider that found the resource.
+    pub provider: uuid::Uuid,
+    /// Name within the provider, e.g. the path relative to the provider root.
     pub name: String,
-    pub path: PathBuf,
+    pub location: Location,
+    #[serde(default)]
+    pub media_type: MediaType,
+    pub metadata: Metadata,
+    /// Locations listed by a playlist, in playlist order.
+    #[serde(default)]
+    pub entries: Vec<Location>,
+    /// Whether playback continues where it was left off, e.g. for podcast
+    /// episodes.
+    #[serde(default)]
+    pub resumable: bool,
+    /// Size and modification time of the file when it was last scanned.
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+}
+
+impl Resource {
+    pub fn file(&self) -> Option<&Path> {
+        match &self.location {
+            Location::File(path) => Some(path),
+            Location::Url(_) | Location::Archive { .. } => None,
+        }
+    }
+
+    /// Path naming the content, its extension tells the file type.
+    pub fn content_path(&self) -> Option<&Path> {
+        match &self.location {
+            Location::File(path) => Some(path),
+            Location::Archive { entry, .. } => Some(Path::new(entry)),
+            Location::Url(_) => None,
+        }
+    }
+
+    pub fn to_web_interface(&self) -> web_interface::Resource {
+        web_interface::Resource {
+            uuid: self.uuid,
+            provider: self.provider,
+            name: self.name.clone(),
+            media_type: self.media_type,
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+/// Namespace of the name-based resource identifiers.
+const RESOURCE_NAMESPACE: uuid::Uuid =
+    uuid::Uuid::from_u128(0x5a8c_a5f1_0c3e_4b7d_9e2a_61d4_f0b8_c3e7);
+
+/// Namespace of the name-based provider identifiers.
+const PROVIDER_NAMESPACE: uuid::Uuid =
+    uuid::Uuid::from_u128(0x0e47_93b2_7d1a_4c65_b8f0_2c9e_5a31_d6f4);
+
+/// Derives the identifier of a provider from its configured name.
+pub fn provider_id(name: &str) -> uuid::Uuid {
+    uuid::Uuid::new_v5(&PROVIDER_NAMESPACE, name.as_bytes())
+}
+
+/// Derives the identifier of a resource from the provider that found it and
+/// its path relative to the provider root, so it survives restarts and rescans.
+pub fn resource_id(provider: &str, relative_path: &str) -> uuid::Uuid {
+    let name = format!("{}\0{}", provider, relative_path);
+    uuid::Uuid::new_v5(&RESOURCE_NAMESPACE, name.as_bytes())
+}
+
+/// Derives the identifier of a file found under the root with index `root`
+/// of a provider with several paths, so equal relative paths under different
+/// roots stay apart. Files under the first root keep the identifier from
+/// [`resource_id`].
+pub fn file_resource_id(provider: &str, root: usize, relative_path: &str) -> uuid::Uuid {
+    match root {
+        0 => resource_id(provider, relative_path),
+        _ => resource_id(provider, &format!("{}\0{}", root, relative_path)),
+    }
 }
@@ -0,0 +1,39 @@
+use crate::json_file;
+use crate::resource::Resource;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Modification times of the directories a filesystem provider read, so
+/// unchanged ones are not read again on the next start.
+pub type Directories = BTreeMap<PathBuf, SystemTime>;
+
+/// Directories scanned by a filesystem provider along with the paths and
+/// extensions it was configured with at the time. If those changed the
+/// directories are all read again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScannedDirectories {
+    pub paths: Vec<PathBuf>,
+    pub extensions: Vec<String>,
+    pub modified: Directories,
+}
+
+/// Catalogue of resources persisted between runs, so they are available
+/// right after startup while the providers check for changes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    pub resources: Vec<Resource>,
+    /// Scanned directories by provider id.
+    #[serde(default)]
+    pub directories: HashMap<uuid::Uuid, ScannedDirectories>,
+}
+
+pub fn load_index<P: AsRef<Path>>(path: P) -> Result<Index> {
+    json_file::load(path)
+}
+
+pub async fn save_index<P: AsRef<Path>>(path: P, index: &Index) -> Result<()> {
+    json_file::save_atomic(path, index).await
+}
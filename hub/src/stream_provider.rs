@@ -0,0 +1,140 @@
+use crate::config::Station;
+use crate::playlist::read_playlist;
+use crate::provider::{reconcile, Provider, Published, ResourceProviderInterface, ScanProgress};
+use crate::resource::{provider_id, resource_id, Location, Resource};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use messages::web_interface::{MediaType, Metadata, ProviderKind, ScanStatus};
+use std::path::Path;
+use std::sync::Mutex;
+use tokio::sync::mpsc::UnboundedSender as Sender;
+
+/// Internet radio stations and other streams, configured directly or read
+/// from an M3U/PLS playlist of stations.
+pub struct StreamProvider {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub stations: Vec<Station>,
+    pub playlist: Option<String>,
+    resource_sender: Sender<ResourceProviderInterface>,
+    known: Mutex<Published>,
+}
+
+impl StreamProvider {
+    pub fn new(
+        name: String,
+        stations: Vec<Station>,
+        playlist: Option<String>,
+        resource_sender: Sender<ResourceProviderInterface>,
+    ) -> Self {
+        let zelf = Self {
+            id: provider_id(&name),
+            name,
+            stations,
+            playlist,
+            resource_sender,
+            known: Mutex::new(Published::new()),
+        };
+
+        if let Err(e) = zelf.reload() {
+            log::error!("Cannot load stations of provider {}: {}", zelf.name, e);
+        }
+
+        zelf
+    }
+
+    fn load_stations(&self) -> anyhow::Result<Vec<Resource>> {
+        let mut stations: Vec<(String, String)> = self
+            .stations
+            .iter()
+            .map(|station| (station.name.clone(), station.url.clone()))
+            .collect();
+
+        if let Some(playlist) = &self.playlist {
+            stations.extend(
+                read_playlist(Path::new(playlist))?
+                    .into_iter()
+                    .map(|entry| {
+                        let location = entry.location;
+                        let name = entry.title.unwrap_or_else(|| location.clone());
+                        (name, location)
+                    }),
+            );
+        }
+
+        Ok(stations
+            .into_iter()
+            .map(|(name, url)| Resource {
+                uuid: resource_id(&self.name, &url),
+                provider: self.id,
+                metadata: Metadata {
+                    title: Some(name.clone()),
+                    ..Default::default()
+                },
+                name,
+                location: Location::Url(url),
+                media_type: MediaType::Audio,
+                entries: Vec::new(),
+                resumable: false,
+                size: None,
+                modified: None,
+            })
+            .collect())
+    }
+
+    /// Loads the stations again and reports the differences to the ones
+    /// published before.
+    fn reload(&self) -> anyhow::Result<usize> {
+        let stations = self.load_stations()?;
+        let count = stations.len();
+
+        let messages = reconcile(&mut self.known.lock().unwrap(), stations);
+        for msg in messages {
+            self.resource_sender
+                .send(msg)
+                .map_err(|_| anyhow!("Resource receiver is gone"))?;
+        }
+
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl Provider for StreamProvider {
+    fn get_id(&self) -> uuid::Uuid {
+        self.id
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_kind(&self) -> ProviderKind {
+        ProviderKind::Stream
+    }
+
+    fn get_roots(&self) -> Vec<String> {
+        self.playlist.iter().cloned().collect()
+    }
+
+    fn get_scan_status(&self) -> ScanStatus {
+        ScanStatus::Idle
+    }
+
+    fn rescan(&self, progress: Sender<ScanProgress>) -> anyhow::Result<()> {
+        let mut report = ScanProgress {
+            provider: self.id,
+            files_visited: self.playlist.iter().count() as u64,
+            finished: true,
+            ..Default::default()
+        };
+
+        match self.reload() {
+            Ok(count) => report.resources_found = count as u64,
+            Err(e) => report.errors.push(e.to_string()),
+        }
+
+        progress.send(report).ok();
+        Ok(())
+    }
+}
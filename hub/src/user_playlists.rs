@@ -0,0 +1,38 @@
+use crate::json_file;
+use anyhow::Result;
+use messages::web_interface;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Playlist created from the web interface, as opposed to playlist files
+/// found by the providers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserPlaylist {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub resources: Vec<uuid::Uuid>,
+}
+
+impl UserPlaylist {
+    pub fn to_web_interface(&self) -> web_interface::Playlist {
+        web_interface::Playlist {
+            id: self.id,
+            name: self.name.clone(),
+            resources: self.resources.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PlaylistsFile {
+    playlists: Vec<UserPlaylist>,
+}
+
+pub fn load_playlists<P: AsRef<Path>>(path: P) -> Result<Vec<UserPlaylist>> {
+    let content: PlaylistsFile = json_file::load(path)?;
+    Ok(content.playlists)
+}
+
+pub async fn save_playlists<P: AsRef<Path>>(path: P, playlists: Vec<UserPlaylist>) -> Result<()> {
+    json_file::save_atomic(path, &PlaylistsFile { playlists }).await
+}
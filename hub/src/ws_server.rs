@@ -2,12 +2,21 @@ use anyhow::{anyhow, Result};
 use futures_util::StreamExt;
 use log::{debug, warn};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
 use tokio::sync::mpsc::unbounded_channel as channel;
 use tokio::sync::mpsc::UnboundedSender as Sender;
+use tokio::time::Instant;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_tungstenite::tungstenite::Message;
 
+/// How often idle connections are pinged.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Connections that did not send anything, not even a pong, for this long are
+/// closed, e.g. those of sinks that lost power or network.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(45);
+
 #[async_trait::async_trait]
 pub trait WebSocketHandler<Request, Response> {
     async fn handle(&self, id: uuid::Uuid, request: Request) -> Response;
@@ -71,8 +80,22 @@ where
 
     let connection_id = hub.add_connection(tx.clone()).await;
 
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    let mut last_seen = Instant::now();
+
     loop {
         tokio::select! {
+            _ = ping.tick() => {
+                if last_seen.elapsed() > CONNECTION_TIMEOUT {
+                    warn!("Connection to {} timed out", addr);
+                    break;
+                }
+
+                use futures_util::SinkExt;
+                if let Err(e) = write.send(Message::Ping(Vec::new())).await {
+                    debug!("Ping could not be sent: {:?}", e);
+                }
+            }
             Some(request) = rx.next() => {
                 let request = serde_json::to_string(&request);
                 match request {
@@ -92,9 +115,13 @@ where
             Some(message) = read.next() => {
                 match message {
                     Ok(message) => {
+                        last_seen = Instant::now();
                         if message.is_close() {
                             break
                         }
+                        if message.is_ping() || message.is_pong() {
+                            continue
+                        }
 
                         match message.to_text() {
                             Ok(message) => {
@@ -1,15 +1,123 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PlaybackState {
+    Stopped,
+    Buffering,
+    Paused,
+    Playing,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Seek {
+    /// Position from the beginning of the stream in milliseconds.
+    Absolute(u64),
+    /// Offset from the current position in milliseconds.
+    Relative(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AudioOutput {
+    pub name: String,
+    pub description: String,
+    pub default: bool,
+}
+
+/// What a sink is able to play and which controls it supports.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Capabilities {
+    pub audio: bool,
+    pub video: bool,
+    /// Caps names of the container formats the sink can demux or parse,
+    /// e.g. `audio/x-flac` or `video/x-matroska`. Empty if unknown.
+    pub containers: Vec<String>,
+    /// Caps names of the streams the sink can decode, e.g. `audio/x-vorbis`.
+    /// Empty if unknown.
+    pub codecs: Vec<String>,
+    pub seekable: bool,
+    pub volume: bool,
+    pub output_selection: bool,
+}
+
+/// Capabilities assumed for sinks that do not announce any, which were
+/// audio-only.
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            audio: true,
+            video: false,
+            containers: Vec::new(),
+            codecs: Vec::new(),
+            seekable: true,
+            volume: true,
+            output_selection: true,
+        }
+    }
+}
+
+/// Playback of a sink that kept playing while it was disconnected from the hub.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PlaybackSnapshot {
+    pub resource: uuid::Uuid,
+    pub state: PlaybackState,
+    pub position_ms: u64,
+    pub duration_ms: Option<u64>,
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum SinkRequest {
-    Register { name: String },
+    Register {
+        /// Persistent identifier generated by the sink, stable across reconnects.
+        id: uuid::Uuid,
+        name: String,
+        #[serde(default)]
+        capabilities: Capabilities,
+        #[serde(default)]
+        playback: Option<PlaybackSnapshot>,
+    },
+    StateChanged(PlaybackState),
+    /// Periodic playback progress, both values in milliseconds.
+    PositionChanged {
+        /// Resource the position belongs to, the hub ignores positions that
+        /// arrive after it started another resource.
+        #[serde(default)]
+        resource: Option<uuid::Uuid>,
+        position_ms: u64,
+        duration_ms: Option<u64>,
+    },
+    EndOfStream,
+    /// Buffering progress in percent.
+    Buffering(u32),
+    Error(String),
+    /// Title announced by the stream itself, e.g. the ICY title of a radio station.
+    TitleChanged(String),
+    /// Linear volume in the range of 0.0 to 1.0.
+    VolumeChanged {
+        volume: f64,
+        muted: bool,
+    },
+    OutputsChanged(Vec<AudioOutput>),
+    /// Name of the output device currently used for playback.
+    OutputChanged(String),
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum SinkResponse {
-    Play(PathBuf),
+    /// Plays the resource streamed from the hub's media server at `url`.
+    Play {
+        resource: uuid::Uuid,
+        url: String,
+        /// Position to start from, e.g. where an episode was left off.
+        #[serde(default)]
+        start_ms: Option<u64>,
+    },
     Pause,
     Stop,
+    Seek(Seek),
+    SetVolume(f64),
+    SetMute(bool),
+    SetOutput(String),
+    Registered,
+    RegistrationRejected(String),
     Dummy,
 }
@@ -1,16 +1,180 @@
+use crate::sink_management::{AudioOutput, Capabilities, PlaybackState, Seek};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct QueryProviders {}
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Title,
+    Artist,
+    Album,
+    Year,
+    Duration,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct QueryResources {
+    /// Words that all have to appear in the name or the tags of a resource.
+    pub search: Option<String>,
+    pub provider: Option<uuid::Uuid>,
+    /// File extensions to include, all if empty.
+    pub extensions: Vec<String>,
+    /// Media types to include, all if empty.
+    pub media_types: Vec<MediaType>,
+    pub sort: SortKey,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// Rescans the provider with the given id or all providers if none is given.
 #[derive(Debug, Clone, Deserialize)]
-pub struct QueryResources {}
+pub struct Rescan {
+    pub provider: Option<uuid::Uuid>,
+}
+
+/// Hierarchical views of the library built from resource metadata.
+#[derive(Debug, Clone, Deserialize)]
+pub enum Browse {
+    Artists,
+    Albums {
+        artist: Option<String>,
+    },
+    Genres,
+    /// Tracks matching all given criteria, ordered by disc and track number.
+    Tracks {
+        artist: Option<String>,
+        album: Option<String>,
+        genre: Option<String>,
+    },
+    /// Direct children of `path` (relative to the provider roots, root if
+    /// empty) of a filesystem provider.
+    Folder {
+        provider: uuid::Uuid,
+        #[serde(default)]
+        path: String,
+    },
+    /// Resources listed by a playlist that are part of the library, in
+    /// playlist order.
+    Playlist {
+        resource: uuid::Uuid,
+    },
+}
+
+/// Changes to the playlists kept by the hub. After every change the hub
+/// sends the updated `Playlists` to all clients.
+#[derive(Debug, Clone, Deserialize)]
+pub enum EditPlaylist {
+    Create {
+        name: String,
+        #[serde(default)]
+        resources: Vec<uuid::Uuid>,
+    },
+    Rename {
+        playlist: uuid::Uuid,
+        name: String,
+    },
+    /// Replaces the resources of the playlist.
+    SetResources {
+        playlist: uuid::Uuid,
+        resources: Vec<uuid::Uuid>,
+    },
+    Delete {
+        playlist: uuid::Uuid,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryQueue {
+    pub sink: uuid::Uuid,
+}
 
 #[derive(Debug, Clone, Deserialize)]
 pub enum Action {
-    Play(uuid::Uuid),
-    Stop,
-    Pause,
+    Play {
+        sink: uuid::Uuid,
+        resource: uuid::Uuid,
+    },
+    Stop {
+        sink: uuid::Uuid,
+    },
+    Pause {
+        sink: uuid::Uuid,
+    },
+    Enqueue {
+        sink: uuid::Uuid,
+        resources: Vec<uuid::Uuid>,
+    },
+    /// Plays the resources of a hub playlist right after the current one.
+    PlayPlaylist {
+        sink: uuid::Uuid,
+        playlist: uuid::Uuid,
+    },
+    EnqueuePlaylist {
+        sink: uuid::Uuid,
+        playlist: uuid::Uuid,
+    },
+    Next {
+        sink: uuid::Uuid,
+    },
+    Previous {
+        sink: uuid::Uuid,
+    },
+    ClearQueue {
+        sink: uuid::Uuid,
+    },
+    RemoveFromQueue {
+        sink: uuid::Uuid,
+        index: usize,
+    },
+    MoveQueueItem {
+        sink: uuid::Uuid,
+        from: usize,
+        to: usize,
+    },
+    Seek {
+        sink: uuid::Uuid,
+        position: Seek,
+    },
+    /// Linear volume in the range of 0.0 to 1.0.
+    SetVolume {
+        sink: uuid::Uuid,
+        volume: f64,
+    },
+    SetMute {
+        sink: uuid::Uuid,
+        mute: bool,
+    },
+    SetOutput {
+        sink: uuid::Uuid,
+        output: String,
+    },
+}
+
+impl Action {
+    pub fn sink(&self) -> uuid::Uuid {
+        match self {
+            Action::Play { sink, .. } => *sink,
+            Action::Stop { sink } => *sink,
+            Action::Pause { sink } => *sink,
+            Action::Enqueue { sink, .. } => *sink,
+            Action::PlayPlaylist { sink, .. } => *sink,
+            Action::EnqueuePlaylist { sink, .. } => *sink,
+            Action::Next { sink } => *sink,
+            Action::Previous { sink } => *sink,
+            Action::ClearQueue { sink } => *sink,
+            Action::RemoveFromQueue { sink, .. } => *sink,
+            Action::MoveQueueItem { sink, .. } => *sink,
+            Action::Seek { sink, .. } => *sink,
+            Action::SetVolume { sink, .. } => *sink,
+            Action::SetMute { sink, .. } => *sink,
+            Action::SetOutput { sink, .. } => *sink,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -18,24 +182,176 @@ pub enum WebUiRequest {
     QuerySinks,
     QueryProviders(QueryProviders),
     QueryResources(QueryResources),
+    QueryQueue(QueryQueue),
     Action(Action),
+    Rescan(Rescan),
+    Browse(Browse),
+    QueryPlaylists,
+    EditPlaylist(EditPlaylist),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Playback {
+    pub resource: Option<uuid::Uuid>,
+    pub state: PlaybackState,
+    pub position_ms: u64,
+    pub duration_ms: Option<u64>,
+    pub buffering: Option<u32>,
+    pub error: Option<String>,
+    /// Title announced by the stream, e.g. the song a radio station plays.
+    pub stream_title: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Sink {
     pub uid: uuid::Uuid,
     pub name: String,
+    pub online: bool,
+    pub capabilities: Capabilities,
+    pub playback: Playback,
+    pub volume: f64,
+    pub muted: bool,
+    pub outputs: Vec<AudioOutput>,
+    pub current_output: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ProviderKind {
+    Filesystem,
+    Stream,
+    Podcast,
+    Archive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ScanStatus {
+    /// The provider has not started scanning yet.
+    Pending,
+    Scanning,
+    Idle,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Provider {
+    pub id: uuid::Uuid,
     pub name: String,
+    pub kind: ProviderKind,
+    pub roots: Vec<String>,
+    pub resources: usize,
+    pub scan_status: ScanStatus,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaType {
+    Audio,
+    Video,
+    Image,
+    Playlist,
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    pub duration_ms: Option<u64>,
+    /// RFC 3339 publication date of podcast episodes.
+    #[serde(default)]
+    pub published: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Resource {
     pub uuid: uuid::Uuid,
+    pub provider: uuid::Uuid,
+    pub name: String,
+    pub media_type: MediaType,
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceList {
+    /// Number of resources matching the query before pagination.
+    pub total: usize,
+    pub offset: usize,
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum BrowseEntry {
+    Artist {
+        name: String,
+        albums: usize,
+        tracks: usize,
+    },
+    Album {
+        name: String,
+        artist: Option<String>,
+        tracks: usize,
+    },
+    Genre {
+        name: String,
+        tracks: usize,
+    },
+    Folder {
+        name: String,
+        path: String,
+        children: usize,
+    },
+    Resource(Box<Resource>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Queue {
+    pub sink: uuid::Uuid,
+    pub items: Vec<Resource>,
+    pub current: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NowPlaying {
+    pub sink: uuid::Uuid,
+    pub resource: Option<Resource>,
+    pub state: PlaybackState,
+    pub position_ms: u64,
+    pub duration_ms: Option<u64>,
+    pub stream_title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourcesChanged {
+    pub added: Vec<Resource>,
+    pub removed: Vec<uuid::Uuid>,
+    pub modified: Vec<Resource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanProgress {
+    pub provider: uuid::Uuid,
+    pub files_visited: u64,
+    pub resources_found: u64,
+    /// Errors encountered since the previous progress message.
+    pub errors: Vec<String>,
+    pub finished: bool,
+}
+
+/// Playlist kept by the hub, listing resources by their ids. Resources
+/// that are currently not part of the library are kept but skipped when the
+/// playlist is played.
+#[derive(Debug, Clone, Serialize)]
+pub struct Playlist {
+    pub id: uuid::Uuid,
     pub name: String,
+    pub resources: Vec<uuid::Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +363,13 @@ pub struct ProblemDetails {
 pub enum WebUiResponse {
     Sinks(Vec<Sink>),
     Providers(Vec<Provider>),
-    Resources(Vec<Resource>),
+    Resources(ResourceList),
+    BrowseEntries(Vec<BrowseEntry>),
+    Queue(Queue),
+    Playlists(Vec<Playlist>),
+    NowPlaying(Box<NowPlaying>),
+    ResourcesChanged(ResourcesChanged),
+    ScanProgress(ScanProgress),
+    Accepted,
     Error(ProblemDetails),
 }